@@ -97,6 +97,8 @@ def make_plots(output_dir):
             for id, vs in landlords[k].items():
                 if id == "-1":
                     plt.plot(range(len(vals)), vs, label='DOMA', color='#f771b4')
+                elif int(id) < 0:
+                    plt.plot(range(len(vals)), vs, label='DOMA {}'.format(-int(id) - 1))
                 else:
                     plt.plot(range(len(vals)), vs, label='Landlord {}'.format(id))
             plt.legend()
@@ -0,0 +1,91 @@
+use super::agent::Tenant;
+use super::city::{City, Unit};
+use super::config::Config;
+use serde::{Serialize, Deserialize};
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use strum_macros::{Display, EnumString};
+
+// How DOMA chooses units to buy
+#[derive(Display, EnumString, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AcquisitionStrategy {
+    // Buys the units shareholders live in,
+    // then in the neighborhoods they live in
+    MemberFirst,
+
+    // Buys units whose tenants are struggling with
+    // the rent, first where rents are rising fastest
+    AntiDisplacement,
+
+    // Buys where it already owns the most
+    NeighborhoodConcentration,
+
+    // Buys cheap units with high rent to price ratios
+    YieldMaximizing,
+}
+
+// What DOMA knows about the city when
+// choosing what to buy; by neighborhood
+pub struct AcquisitionContext {
+    // Share of members living there
+    pub member_share: Vec<f32>,
+
+    // Share of DOMA's units there
+    pub holdings: Vec<f32>,
+
+    // Change in rents over the past
+    // year, as a ratio
+    pub rent_growth: Vec<f32>,
+
+    // Units members live in
+    pub member_homes: BTreeSet<usize>,
+}
+
+impl AcquisitionStrategy {
+    // How much the strategy wants the unit
+    fn score(&self, unit: &Unit, neighb_id: usize, tenants: &[Tenant], context: &AcquisitionContext, conf: &Config) -> f32 {
+        match self {
+            AcquisitionStrategy::MemberFirst => {
+                let share = context.member_share.get(neighb_id).cloned().unwrap_or(0.);
+                if context.member_homes.contains(&unit.id) {
+                    1. + share
+                } else {
+                    share
+                }
+            },
+            AcquisitionStrategy::AntiDisplacement => {
+                let rent: f32 = unit.tenants.iter().map(|&t_id| tenants[t_id].last_rent).sum();
+                let income: f32 = unit.tenants.iter().map(|&t_id| tenants[t_id].income).sum();
+                let burden = if income > 0. { rent / income } else { 1. };
+                let at_risk = burden > conf.cost_burden || unit.arrears > 0. || unit.eviction.is_some();
+                if at_risk && !unit.vacant() {
+                    context.rent_growth[neighb_id] * (1. + burden)
+                } else {
+                    0.
+                }
+            },
+            AcquisitionStrategy::NeighborhoodConcentration => {
+                context.holdings[neighb_id]
+            },
+            AcquisitionStrategy::YieldMaximizing => 0.,
+        }
+    }
+
+    // Candidate units, most wanted first. Ties, and everything
+    // for the yield-maximizing strategy, go to cheap units
+    // with high rent to price ratios, i.e. low value²/rent.
+    pub fn rank(&self, candidates: Vec<usize>, city: &City, tenants: &[Tenant], context: &AcquisitionContext, conf: &Config) -> Vec<usize> {
+        let mut scored: Vec<(usize, f32, f32)> = candidates.into_iter().filter_map(|u_id| {
+            let unit = &city.units[u_id];
+            let neighb_id = city.parcels.get(&unit.pos).unwrap().neighborhood?;
+            let score = self.score(unit, neighb_id, tenants, context, conf);
+            let cost = (unit.value * unit.value / (unit.rent + 1.)).round();
+            Some((u_id, score, cost))
+        }).collect();
+        scored.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal)
+                .then(a.2.partial_cmp(&b.2).unwrap_or(Ordering::Equal))
+        });
+        scored.into_iter().map(|(u_id, _, _)| u_id).collect()
+    }
+}
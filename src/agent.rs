@@ -1,6 +1,13 @@
 use super::city::{City, Parcel, Unit};
+use std::cmp::max;
 use super::config::Config;
+use super::events::{EventKind, EventLog, RentChangeReason};
+use super::acquisition::{AcquisitionContext, AcquisitionStrategy};
+use super::economy::Economy;
+use super::eviction::{Eviction, EvictionReason, Protections};
+use super::finance::{Bond, Mortgage};
 use super::grid::Position;
+use super::rent::{MarketEstimate, RentStrategy};
 use fnv::FnvHashMap;
 use linreg::linear_regression;
 use rand::distributions::WeightedIndex;
@@ -8,170 +15,161 @@ use rand::prelude::*;
 use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
 use rand::Rng;
+use rand_distr::StandardNormal;
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
 use strum_macros::Display;
 
-fn distance(a: Position, b: Position) -> f32 {
+pub fn distance(a: Position, b: Position) -> f32 {
     (((a.0 - b.0).pow(2) + (a.1 - b.1).pow(2)) as f32).sqrt()
 }
 
-#[derive(Display, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Display, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum AgentType {
     Tenant,
     Landlord,
     DOMA,
 }
 
-#[derive(Debug)]
+#[derive(Display, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Employment {
+    Child,
+    Employed,
+    Unemployed,
+    Retired,
+}
+
+// Age, in years, at which children
+// start looking for work
+pub const ADULT_AGE: usize = 18;
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Tenant {
     pub id: usize,
     pub income: f32, // monthly
+    pub wage: f32, // monthly, when employed
+    pub employment: Employment,
+    pub age: usize, // months
+    pub household: usize,
     pub unit: Option<usize>,
     pub work: Position,
     pub units: Vec<usize>,
     pub last_dividend: f32,
-    pub player: bool
+    pub player: bool,
+
+    // Ledger: money set aside, and housing costs paid last
+    // month (rent, or mortgage and upkeep for owner-occupiers)
+    pub savings: f32,
+    pub last_rent: f32,
+
+    // Loans on units the tenant owns
+    pub mortgages: Vec<Mortgage>,
+
+    // Died or left the city
+    pub departed: bool,
+
+    // Times evicted
+    pub evictions: usize,
 }
 
 impl Tenant {
-    pub fn step(
-        &mut self,
-        city: &mut City,
-        month: usize,
-        vacant_units: &mut Vec<usize>,
-        rng: &mut StdRng,
-        conf: &Config,
-    ) {
-        let mut reconsider;
-        let mut current_desirability = 0.;
-        let mut moving_penalty = conf.moving_penalty;
-
-        match self.unit {
-            // If currently w/o home,
-            // will always look for a place to move into,
-            // with no moving penalty
-            None => {
-                reconsider = true;
-                current_desirability = -1.;
-                moving_penalty = 0.;
-            }
+    // A month of working life: wages drift, jobs are lost and found
+    // (more easily where the local economy is doing well)
+    // and tenants retire. `activity` is the economic activity
+    // of the neighborhood the tenant works in, from -1 to 1.
+    pub fn update_income(&mut self, activity: f32, inflation: f32, rng: &mut StdRng, conf: &Config) -> Option<EventKind> {
+        let roll: f32 = rng.gen();
+        let shock: f32 = rng.sample(StandardNormal);
+        self.age += 1;
+        self.wage *= 1. + inflation / 12.;
 
-            // Otherwise, only consider moving
-            // between leases or if their current
-            // place is no longer affordable
-            Some(u_id) => {
-                let unit = &mut city.units[u_id];
-                let elapsed = if month > unit.lease_month {
-                    month - unit.lease_month
+        let mut event = None;
+        match self.employment {
+            Employment::Employed => {
+                let p_loss = conf.job_loss_rate * (1. - conf.commercial_sensitivity * activity);
+                if self.age >= conf.retirement_age * 12 {
+                    self.employment = Employment::Retired;
+                    event = Some(EventKind::Retired { tenant: self.id });
+                } else if roll < p_loss {
+                    self.employment = Employment::Unemployed;
+                    event = Some(EventKind::JobLost { tenant: self.id });
                 } else {
-                    0
-                };
-                reconsider = elapsed > 0 && elapsed % 12 == 0;
-                if !reconsider {
-                    // No longer can afford
-                    let parcel = &city.parcels.get(&unit.pos).unwrap();
-                    current_desirability = self.desirability(unit, parcel);
-                    if current_desirability == 0. {
-                        reconsider = true;
-                        unit.tenants.remove(&self.id);
-                        vacant_units.push(u_id);
-                        self.unit = None;
-                    }
+                    self.wage *= f32::max(0., 1. + conf.wage_growth / 12. + conf.wage_volatility * shock);
                 }
             }
-        }
-        if reconsider && vacant_units.len() > 0 {
-            let sample = vacant_units.choose_multiple(rng, conf.tenant_sample_size);
-            let (best_id, best_desirability) = sample.fold((0, 0.), |acc, &u_id| {
-                let u = &city.units[u_id];
-                let p = &city.parcels.get(&u.pos).unwrap();
-                if u.vacancies() <= 0 {
-                    acc
-                } else {
-                    let desirability = if conf.debug && u.is_doma() {
-                        // If playing (i.e. debug=false), bots
-                        // leave DOMA units for players to choose from
-                        0.
-                    } else {
-                        self.desirability(u, p)
-                    };
-                    if desirability > acc.1 {
-                        (u_id, desirability)
-                    } else {
-                        acc
-                    }
+            Employment::Unemployed => {
+                let p_find = conf.job_find_rate * (1. + conf.commercial_sensitivity * activity);
+                if self.age >= conf.retirement_age * 12 {
+                    self.employment = Employment::Retired;
+                    event = Some(EventKind::Retired { tenant: self.id });
+                } else if roll < p_find {
+                    self.employment = Employment::Employed;
+                    event = Some(EventKind::Hired { tenant: self.id });
                 }
-            });
-            if best_desirability > 0. && best_desirability - moving_penalty > current_desirability {
-                match self.unit {
-                    Some(u_id) => {
-                        let unit = &mut city.units[u_id];
-                        unit.tenants.remove(&self.id);
-                        vacant_units.push(u_id);
-                    }
-                    None => {}
-                }
-
-                self.unit = Some(best_id);
-                let unit = &mut city.units[best_id];
-
-                // If unit was vacant, this is a new lease
-                if unit.vacant() {
-                    unit.lease_month = month % 12;
-                }
-
-                unit.tenants.insert(self.id);
-
-                // Remove unit if it no longer has
-                // any vacancies
-                if unit.vacancies() == 0 {
-                    vacant_units.retain(|&u_id| u_id != best_id);
+            }
+            Employment::Child => {
+                if self.age >= ADULT_AGE * 12 {
+                    self.employment = Employment::Unemployed;
                 }
             }
+            Employment::Retired => {}
         }
+        self.income = self.current_income(conf);
+        event
     }
 
+    pub fn current_income(&self, conf: &Config) -> f32 {
+        match self.employment {
+            Employment::Child => 0.,
+            Employment::Employed => self.wage,
+            Employment::Unemployed => self.wage * conf.unemployment_benefit,
+            Employment::Retired => self.wage * conf.retirement_income,
+        }
+    }
+
+    // The most rent the tenant would put towards their
+    // household's rent; see the affordability limit
+    // in `Household::desirability`
+    pub fn willingness_to_pay(&self) -> f32 {
+        self.income/3. + self.last_dividend
+    }
+
+    // The tenant's share of the rent, split
+    // evenly among the household, after dividends
     pub fn adjusted_rent(&self, unit: &Unit) -> f32 {
-        // let rent_per_tenant = f32::max(1., unit.rent / unit.tenants.len() as f32);
-        let rent_per_tenant = f32::max(1., unit.rent / unit.occupancy as f32);
+        let rent_per_tenant = f32::max(1., unit.rent / max(1, unit.tenants.len()) as f32);
         rent_per_tenant - f32::min(rent_per_tenant, self.last_dividend)
     }
 
-    pub fn desirability(&self, unit: &Unit, parcel: &Parcel) -> f32 {
-        let n_tenants = (unit.tenants.len() + 1) as f32;
-
-        // Adjust rent by last DOMA dividend
-        // let rent_per_tenant = f32::max(1., unit.rent / n_tenants);
-        let rent_per_tenant = f32::max(1., unit.rent / unit.occupancy as f32);
-        let adjusted_rent_per_tenant = rent_per_tenant - f32::min(rent_per_tenant, self.last_dividend);
+    pub fn debt(&self) -> f32 {
+        self.mortgages.iter().map(|m| m.balance).sum()
+    }
 
-        // Tenants can spend at most 1/3 of income on rent
-        if self.income/3. < adjusted_rent_per_tenant {
-            0.
-        } else {
-            let ratio = (self.income / adjusted_rent_per_tenant).sqrt();
-            let spaciousness = f32::max(unit.area / n_tenants, 0.).powf(1. / 32.);
-            let commute_distance = distance(self.work, unit.pos);
-            let commute: f32 = if commute_distance == 0. {
-                1.
-            } else {
-                1. / commute_distance
-            };
-            ratio * (spaciousness + parcel.desirability + unit.condition + commute)
-        }
+    pub fn remove_mortgage(&mut self, unit: usize) -> f32 {
+        let balance = self.mortgages.iter()
+            .filter(|m| m.unit == unit)
+            .map(|m| m.balance)
+            .sum();
+        self.mortgages.retain(|m| m.unit != unit);
+        balance
     }
 
     pub fn check_purchase_offers(
         &mut self,
         city: &mut City,
         price_to_rent_ratio: f32,
+        events: &mut EventLog,
     ) -> Vec<(AgentType, usize, usize, f32)> {
         // If they own units,
         // check purchase offers
         let mut transfers = Vec::new();
         for &u in &self.units {
-            let mut unit = &mut city.units[u];
+            let unit = &mut city.units[u];
             if unit.offers.len() == 0 {
                 continue;
+            } else if self.unit == Some(u) {
+                // Owner-occupiers don't sell their home
+                emit_offer_results(unit, (AgentType::Tenant, self.id, 0.), events);
             } else {
                 // This should reflect the following:
                 // - since rents decrease as the apartment is vacant,
@@ -193,7 +191,10 @@ impl Tenant {
                             }
                         },
                     );
+                emit_offer_results(unit, (typ, landlord, best_amount), events);
                 if best_amount > 0. {
+                    events.emit(EventKind::Sale {
+                        unit: u, seller: (AgentType::Tenant, self.id), buyer: (typ, landlord), price: best_amount });
                     unit.value = best_amount;
                     unit.owner = (typ, landlord);
                     transfers.push((typ, landlord, u, best_amount));
@@ -203,97 +204,259 @@ impl Tenant {
             unit.offers.clear();
         }
 
-        // Remove sold units
-        for (_, _, unit_id, _) in &transfers {
-            self.units.retain(|u_id| u_id != unit_id);
+        // Remove sold units, paying
+        // off their mortgages
+        for &(_, _, unit_id, price) in &transfers {
+            self.units.retain(|&u_id| u_id != unit_id);
+            self.savings += price - self.remove_mortgage(unit_id);
         }
         transfers
     }
 }
 
-#[derive(Debug)]
+// The best offer is accepted (if its amount is above zero),
+// the rest are rejected
+fn emit_offer_results(unit: &Unit, best: (AgentType, usize, f32), events: &mut EventLog) {
+    let (typ, landlord, best_amount) = best;
+    let mut accepted = false;
+    for &(o_typ, o_landlord, amount) in &unit.offers {
+        let buyer = (o_typ, o_landlord);
+        if !accepted && best_amount > 0. && buyer == (typ, landlord) && amount == best_amount {
+            accepted = true;
+            events.emit(EventKind::OfferAccepted { unit: unit.id, buyer, amount });
+        } else {
+            events.emit(EventKind::OfferRejected { unit: unit.id, buyer, amount });
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Landlord {
     pub id: usize,
     pub units: Vec<usize>,
     pub maintenance: f32,
-    pub rent_obvs: FnvHashMap<usize, Vec<f32>>,
-    pub trend_ests: FnvHashMap<usize, f32>,
-    pub invest_ests: FnvHashMap<usize, f32>,
+    pub strategy: RentStrategy,
+
+    // Indexed by neighborhood id
+    pub rent_obvs: Vec<Vec<f32>>,
+    pub trend_ests: Vec<f32>,
+    pub invest_ests: Vec<f32>,
+
+    // Market rent per area, per unit of parcel desirability,
+    // and share of units vacant; by neighborhood
+    pub rent_ests: Vec<f32>,
+    pub vacancy_ests: Vec<f32>,
+
+    // Balance sheet
+    pub cash: f32,
+    pub mortgages: Vec<Mortgage>,
+
+    // Last month's rental income
+    // and expenses (maintenance and mortgages)
+    pub income: f32,
+    pub expenses: f32,
+
+    // Consecutive months with negative cash
+    pub months_negative: usize,
 }
 
 impl Landlord {
-    pub fn new(id: usize, n_neighborhoods: usize) -> Landlord {
-        let mut rent_obvs = FnvHashMap::default();
-        let mut trend_ests = FnvHashMap::default();
-        let mut invest_ests = FnvHashMap::default();
-        for id in 0..n_neighborhoods {
-            rent_obvs.insert(id, Vec::new());
-            trend_ests.insert(id, 0.);
-            invest_ests.insert(id, 0.);
-        }
-
+    pub fn new(id: usize, n_neighborhoods: usize, strategy: RentStrategy, cash: f32) -> Landlord {
         Landlord {
             id: id,
             units: Vec::new(),
-            rent_obvs: rent_obvs,
-            trend_ests: trend_ests,
-            invest_ests: invest_ests,
+            strategy,
+            cash,
+            mortgages: Vec::new(),
+            income: 0.,
+            expenses: 0.,
+            months_negative: 0,
+            rent_obvs: vec![Vec::new(); n_neighborhoods],
+            trend_ests: vec![0.; n_neighborhoods],
+            invest_ests: vec![0.; n_neighborhoods],
+            rent_ests: vec![0.; n_neighborhoods],
+            vacancy_ests: vec![0.; n_neighborhoods],
             maintenance: 0.01,
         }
     }
 
+    pub fn debt(&self) -> f32 {
+        self.mortgages.iter().map(|m| m.balance).sum()
+    }
+
+    // Unit with the largest mortgage, if any
+    pub fn most_indebted_unit(&self) -> Option<usize> {
+        self.mortgages.iter()
+            .fold(None, |acc: Option<&Mortgage>, m| match acc {
+                Some(best) if best.balance >= m.balance => Some(best),
+                _ => Some(m)
+            })
+            .map(|m| m.unit)
+    }
+
+    // Pay for a purchased unit, financing
+    // whatever isn't put down in cash at the going rate
+    pub fn buy(&mut self, unit: usize, price: f32, economy: &Economy, conf: &Config) {
+        let down = f32::min(f32::max(self.cash, 0.), price * economy.down_payment(conf));
+        self.cash -= down;
+        self.units.push(unit);
+        if price > down {
+            self.mortgages.push(Mortgage::new(unit, price - down, economy.interest_rate, conf.mortgage_term));
+        }
+    }
+
+    // Receive the proceeds of a sale,
+    // paying off the unit's mortgage
+    pub fn sell(&mut self, unit: usize, price: f32) {
+        let balance = self.remove_mortgage(unit);
+        self.units.retain(|&u_id| u_id != unit);
+        self.cash += price - balance;
+    }
+
+    // Lose a unit to the lender. The sale pays off as much
+    // of the mortgage as it can, the lender takes any shortfall.
+    pub fn foreclose(&mut self, unit: usize, price: f32) {
+        let balance = self.remove_mortgage(unit);
+        self.units.retain(|&u_id| u_id != unit);
+        self.cash += f32::max(0., price - balance);
+        self.months_negative = 0;
+    }
+
+    // The lender extends the unit's mortgage
+    // over a new term at the going rate, lowering payments
+    pub fn restructure(&mut self, unit: usize, economy: &Economy, conf: &Config) {
+        let balance = self.remove_mortgage(unit);
+        self.mortgages.push(Mortgage::new(unit, balance, economy.interest_rate, conf.mortgage_term));
+        self.months_negative = 0;
+    }
+
+    fn remove_mortgage(&mut self, unit: usize) -> f32 {
+        let balance = self.mortgages.iter()
+            .filter(|m| m.unit == unit)
+            .map(|m| m.balance)
+            .sum();
+        self.mortgages.retain(|m| m.unit != unit);
+        balance
+    }
+
+    pub fn market_estimate(&self, unit: &Unit, parcel: &Parcel) -> MarketEstimate {
+        match parcel.neighborhood {
+            Some(neighb_id) if self.rent_ests[neighb_id] > 0. => {
+                // Projected change in the top of the market
+                let growth = match self.rent_obvs[neighb_id].last() {
+                    Some(&last) if last > 0. && self.trend_ests[neighb_id] > 0. =>
+                        (self.trend_ests[neighb_id]/last).clamp(0.8, 1.25),
+                    _ => 1.
+                };
+                MarketEstimate {
+                    rent: self.rent_ests[neighb_id] * unit.area * parcel.desirability,
+                    growth,
+                    vacancy: self.vacancy_ests[neighb_id],
+                }
+            },
+
+            // No comparable units yet
+            _ => MarketEstimate {
+                rent: unit.rent,
+                growth: 1.,
+                vacancy: 0.,
+            }
+        }
+    }
+
     pub fn step(
         &mut self,
         city: &mut City,
         month: usize,
-        price_to_rent_ratio: f32,
+        economy: &Economy,
         rent_freeze: bool,
         market_tax: bool,
+        protections: Protections,
         rng: &mut StdRng,
         conf: &Config,
-    ) {
-        // Update market estimates
-        self.estimate_rents(city, rng, conf.sample_size);
-        self.estimate_trends(conf.trend_months);
-
+        tenants: &[Tenant],
+        events: &mut EventLog,
+    ) -> Vec<(usize, EvictionReason)> {
         // Maintenance
         for &u in &self.units {
-            let mut unit = &mut city.units[u];
+            let unit = &mut city.units[u];
             let decay: f32 = rng.gen();
             unit.condition -= decay * 0.1; // TODO deterioration rate based on build year?
             unit.condition += self.maintenance;
             unit.condition = f32::min(f32::max(unit.condition, 0.), 1.);
         }
 
-        // Manage units
+        // Manage units, setting rents
+        // according to the landlord's strategy
         for &u in &self.units {
-            let mut unit = &mut city.units[u];
+            let parcel = city.parcels.get(&city.units[u].pos).unwrap();
+            let market = self.market_estimate(&city.units[u], parcel);
+            let unit = &mut city.units[u];
+            let old_rent = unit.rent;
             if unit.vacant() {
                 unit.months_vacant += 1;
-                if unit.months_vacant % 2 == 0 {
-                    unit.rent = unit.rent * 0.98;
-                    // TODO u.maintenance += 0.01
+                unit.rent = self.strategy.asking_rent(unit.rent, &market, unit.months_vacant, conf);
+                if unit.rent != old_rent {
+                    events.emit(EventKind::RentChanged {
+                        unit: u, old_rent, new_rent: unit.rent, reason: RentChangeReason::Vacancy });
                 }
             } else {
                 // Year-long leases
                 let elapsed = month as i32 - unit.lease_month as i32;
-                if !rent_freeze && elapsed > 0 && elapsed % 12 == 0 {
-                    // TODO this can be smarter
-                    // i.e. depend on gap b/w
-                    // current rent and rent estimate/projection
-                    unit.rent = unit.rent * conf.rent_increase_rate;
-                    // TODO u.maintenance -= 0.01
+                if elapsed > 0 && elapsed % 12 == 0 {
+                    let wtp = unit.tenants.iter()
+                        .map(|&t_id| tenants[t_id].willingness_to_pay())
+                        .sum();
+                    let mut rent = self.strategy.renewal_rent(unit.rent, &market, wtp, conf);
+                    if rent_freeze {
+                        rent = f32::min(rent, unit.rent);
+                    }
+                    unit.rent = rent;
+                    if unit.rent != old_rent {
+                        events.emit(EventKind::RentChanged {
+                            unit: u, old_rent, new_rent: unit.rent, reason: RentChangeReason::LeaseRenewal });
+                    }
                 }
             }
         }
 
+        // Collect rent, pay for maintenance and mortgages
+        let mut income = 0.;
+        let mut expenses = 0.;
+        for &u in &self.units {
+            let unit = &city.units[u];
+            income += unit.rent_paid;
+            expenses += unit.value * conf.landlord_maintenance_cost / 12.;
+        }
+        for mortgage in &mut self.mortgages {
+            expenses += mortgage.pay();
+        }
+        self.mortgages.retain(|m| !m.paid_off());
+        self.cash += income - expenses;
+        self.income = income;
+        self.expenses = expenses;
+
+        // Landlords short on cash list their
+        // most indebted unit at a discount
+        if self.cash < 0. {
+            self.months_negative += 1;
+            if let Some(u) = self.most_indebted_unit() {
+                let unit = &mut city.units[u];
+                unit.value *= 0.95;
+            }
+        } else {
+            self.months_negative = 0;
+        }
+
+        let evicted = self.pursue_evictions(city, month, protections, rng, conf, events);
+
         // Make purchase offers
         // Choose random neighborhood weighted by investment potential
-        if !market_tax {
-            let neighbs: Vec<usize> = self.invest_ests.keys().cloned().collect();
+        if !market_tax && self.cash > 0. {
+            let neighbs: Vec<usize> = (0..self.invest_ests.len()).collect();
             let neighb_weights: Vec<f32> = neighbs
                 .iter()
-                .map(|neighb_id| f32::max(0., self.invest_ests[neighb_id]))
+                .map(|&neighb_id| f32::max(0., self.invest_ests[neighb_id]))
                 .collect();
             let neighb_id = if neighb_weights.iter().all(|&w| w == 0.) {
                 *neighbs.choose(rng).unwrap()
@@ -301,18 +464,126 @@ impl Landlord {
                 let neighb_dist = WeightedIndex::new(&neighb_weights).unwrap();
                 neighbs[neighb_dist.sample(rng)]
             };
-            let est_future_rent = self.trend_ests[&neighb_id];
+            let est_future_rent = self.trend_ests[neighb_id];
             let sample = city.units_by_neighborhood[neighb_id].choose_multiple(rng, conf.sample_size);
+            let mut committed = 0.;
             for &u_id in sample {
                 let unit = &mut city.units[u_id];
+                if unit.owner == (AgentType::Landlord, self.id) {
+                    continue;
+                }
                 let parcel = &city.parcels.get(&unit.pos).unwrap();
-                let est_value =
-                    est_future_rent * unit.area * 12. * price_to_rent_ratio * parcel.desirability; // TODO was *100
+                let est_rent = est_future_rent * unit.area * parcel.desirability;
+                let est_value = est_rent * 12. * economy.price_to_rent_ratio; // TODO was *100
                 if est_value > 0. && est_value > unit.value {
+                    // Only offer what can be financed: the down payment
+                    // comes out of cash, and the expected rent
+                    // has to cover the mortgage payments
+                    let down = est_value * economy.down_payment(conf);
+                    let payment = Mortgage::payment(est_value - down, economy.interest_rate, conf.mortgage_term);
+                    if committed + down > self.cash || est_rent < payment {
+                        continue;
+                    }
+                    committed += down;
                     unit.offers.push((AgentType::Landlord, self.id, est_value));
+                    events.emit(EventKind::OfferMade {
+                        unit: u_id, buyer: (AgentType::Landlord, self.id), amount: est_value });
+                }
+            }
+        }
+        evicted
+    }
+
+    // File eviction cases, for nonpayment once arrears build up
+    // or no-fault to sell or renovate, and see them through court.
+    // Returns the units whose households are to be evicted.
+    fn pursue_evictions(
+        &mut self,
+        city: &mut City,
+        month: usize,
+        protections: Protections,
+        rng: &mut StdRng,
+        conf: &Config,
+        events: &mut EventLog,
+    ) -> Vec<(usize, EvictionReason)> {
+        let mut evicted = Vec::new();
+
+        // Landlords short on cash want
+        // their listed unit empty to sell
+        let listed = if self.cash < 0. {
+            self.most_indebted_unit()
+        } else {
+            None
+        };
+        for &u in &self.units {
+            let unit = &mut city.units[u];
+            let renovation_cost = unit.value * conf.renovation_cost;
+            let roll: f32 = rng.gen();
+            let renovate = unit.condition < conf.renovation_condition
+                && self.cash > renovation_cost
+                && roll < conf.renovation_rate;
+
+            // Empty units can be renovated right away
+            if unit.vacant() {
+                if renovate {
+                    self.cash -= renovation_cost;
+                    unit.condition = 1.;
+                }
+                continue;
+            }
+
+            let behind = unit.arrears >= conf.eviction_arrears_months * unit.rent;
+            match unit.eviction.take() {
+                // Tenants who catch up on
+                // rent get the case withdrawn
+                Some(eviction) if eviction.reason == EvictionReason::Nonpayment && !behind => {
+                    for &t_id in &unit.tenants {
+                        events.emit(EventKind::EvictionDismissed { tenant: t_id, unit: u, reason: eviction.reason });
+                    }
+                }
+                Some(eviction) if month >= eviction.hearing => {
+                    if eviction.judgment(protections, rng, conf) {
+                        if eviction.reason == EvictionReason::Renovation {
+                            self.cash -= renovation_cost;
+                            unit.condition = 1.;
+                        }
+                        evicted.push((u, eviction.reason));
+                    } else {
+                        for &t_id in &unit.tenants {
+                            events.emit(EventKind::EvictionDismissed { tenant: t_id, unit: u, reason: eviction.reason });
+                        }
+                    }
+                }
+                Some(eviction) => unit.eviction = Some(eviction),
+                None => {
+                    let reason = if behind {
+                        Some(EvictionReason::Nonpayment)
+                    } else if protections.just_cause {
+                        None
+                    } else if listed == Some(u) {
+                        Some(EvictionReason::Sale)
+                    } else if renovate {
+                        Some(EvictionReason::Renovation)
+                    } else {
+                        None
+                    };
+                    if let Some(reason) = reason {
+                        for &t_id in &unit.tenants {
+                            events.emit(EventKind::EvictionFiled { tenant: t_id, unit: u, reason });
+                        }
+                        unit.eviction = Some(Eviction::file(reason, month, protections, conf));
+                    }
                 }
             }
         }
+        evicted
+    }
+
+    // Update market estimates. Only reads the city,
+    // so this runs for all landlords in parallel.
+    pub fn update_estimates(&mut self, city: &City, rng: &mut StdRng, conf: &Config) {
+        self.estimate_rents(city, rng, conf.sample_size);
+        self.estimate_trends(conf.trend_months);
     }
 
     fn estimate_rents(&mut self, city: &City, rng: &mut StdRng, sample_size: usize) {
@@ -331,27 +602,40 @@ impl Landlord {
             }
         }
 
-        for (&neighb_id, rent_history) in &mut self.rent_obvs {
+        for (neighb_id, rent_history) in self.rent_obvs.iter_mut().enumerate() {
             let n = neighborhoods.entry(neighb_id).or_insert(Vec::new());
-            let sample = city.units_by_neighborhood[neighb_id]
+            let sample: Vec<&Unit> = city.units_by_neighborhood[neighb_id]
                 .choose_multiple(rng, sample_size)
-                .map(|&u_id| city.units[u_id].rent_per_area());
-            n.extend(sample);
+                .map(|&u_id| &city.units[u_id])
+                .collect();
+            n.extend(sample.iter().map(|u| u.rent_per_area()));
             let max_rent = n.iter().cloned().fold(-1., f32::max);
             rent_history.push(max_rent);
+
+            // Rents that tenants are actually paying
+            // are the market rate, adjusted for desirability
+            let occupied: Vec<f32> = sample.iter()
+                .filter(|u| !u.vacant())
+                .map(|u| u.rent_per_area() / f32::max(city.parcels.get(&u.pos).unwrap().desirability, 0.01))
+                .collect();
+            if !occupied.is_empty() {
+                self.rent_ests[neighb_id] = occupied.iter().sum::<f32>() / occupied.len() as f32;
+            }
+            if !sample.is_empty() {
+                self.vacancy_ests[neighb_id] = 1. - occupied.len() as f32 / sample.len() as f32;
+            }
         }
     }
 
     fn estimate_trends(&mut self, trend_months: usize) {
-        for (&neighb_id, rent_history) in &self.rent_obvs {
+        for (neighb_id, rent_history) in self.rent_obvs.iter().enumerate() {
             if rent_history.len() >= trend_months {
                 let ys = &rent_history[rent_history.len() - trend_months..];
                 let xs: Vec<f32> = (0..ys.len()).map(|v| v as f32).collect();
                 let (slope, intercept): (f32, f32) = linear_regression(&xs, &ys).unwrap();
                 let est_market_rent = (trend_months as f32) * slope + intercept;
-                self.trend_ests.insert(neighb_id, est_market_rent);
-                self.invest_ests
-                    .insert(neighb_id, est_market_rent - ys.last().unwrap());
+                self.trend_ests[neighb_id] = est_market_rent;
+                self.invest_ests[neighb_id] = est_market_rent - ys.last().unwrap();
             } else {
                 continue;
             }
@@ -362,10 +646,12 @@ impl Landlord {
         &mut self,
         city: &mut City,
         price_to_rent_ratio: f32,
+        events: &mut EventLog,
     ) -> Vec<(AgentType, usize, usize, f32)> {
         let mut transfers = Vec::new();
-        for &u in &self.units {
-            let mut unit = &mut city.units[u];
+        let units = self.units.clone();
+        for u in units {
+            let unit = &mut city.units[u];
             if unit.offers.len() == 0 {
                 continue;
             } else {
@@ -374,10 +660,16 @@ impl Landlord {
                 //   the longer the vacancy, the more likely they are to sell
                 // - maintenance costs become too much
                 let parcel = &city.parcels.get(&unit.pos).unwrap();
-                let est_future_rent = self.trend_ests[&parcel.neighborhood.unwrap()];
-                let est_value =
+                let est_future_rent = self.trend_ests[parcel.neighborhood.unwrap()];
+                let mut est_value =
                     est_future_rent * unit.area * 12. * price_to_rent_ratio * parcel.desirability;
 
+                // Landlords short on cash will sell at
+                // the listed value rather than holding out
+                if self.cash < 0. {
+                    est_value = f32::min(est_value, unit.value);
+                }
+
                 // Find best offer, if any
                 // and mark offers as rejected or accepted
                 let (typ, landlord, best_amount): (AgentType, usize, f32) =
@@ -391,32 +683,53 @@ impl Landlord {
                             }
                         },
                     );
+                emit_offer_results(unit, (typ, landlord, best_amount), events);
                 if best_amount > 0. {
+                    events.emit(EventKind::Sale {
+                        unit: u, seller: (AgentType::Landlord, self.id), buyer: (typ, landlord), price: best_amount });
                     unit.value = best_amount;
                     unit.owner = (typ, landlord);
+                    unit.offers.clear();
                     transfers.push((typ, landlord, u, best_amount));
+                    self.sell(u, best_amount);
+                    continue;
                 }
             }
-
-            // TODO
-            // best_offer.landlord.property_fund -= best_offer.amount
             unit.offers.clear();
         }
-
-        for (_, _, unit_id, _) in &transfers {
-            self.units.retain(|u_id| u_id != unit_id);
-        }
         transfers
     }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct DOMA {
+    pub id: usize,
+
+    // Neighborhoods it buys in; empty for citywide
+    pub neighborhoods: Vec<usize>,
+
+    // Set once taken over by another DOMA
+    pub merged_into: Option<usize>,
+
     pub funds: f32,
     pub raised: f32,
-    pub shares: FnvHashMap<usize, f32>,
+    pub shares: BTreeMap<usize, f32>,
+
+    // Shares earned but not yet vested, by tenant,
+    // as (shares, month they vest)
+    pub vesting: BTreeMap<usize, Vec<(f32, usize)>>,
     pub units: Vec<usize>,
     maintenance: f32,
 
+    // Units up for sale
+    pub listed: Vec<usize>,
+
+    pub strategy: AcquisitionStrategy,
+
+    // Mean rent per area over the
+    // past year, by neighborhood
+    pub rent_obvs: Vec<Vec<f32>>,
+
     // Percent of rent paid to DOMA
     // that converts to shares
     pub p_rent_share: f32,
@@ -424,36 +737,277 @@ pub struct DOMA {
     pub p_expenses: f32,
 
     pub rent_income_limit: Option<f32>,
+
+    // Borrowing from lenders and members
+    pub mortgages: Vec<Mortgage>,
+    pub bonds: Vec<Bond>,
+
+    // Last month's mortgage and bond payments
+    pub debt_service: f32,
+
+    // Consecutive months with negative funds
+    pub months_negative: usize,
+}
+
+// Settings for a DOMA-like organization beyond the
+// first. Parameters left out take the DOMA_* settings.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DOMASpec {
+    pub starting_funds: f32,
+
+    // Neighborhoods it buys in; empty for citywide
+    #[serde(default)]
+    pub neighborhoods: Vec<usize>,
+
+    pub p_rent_share: Option<f32>,
+    pub p_reserves: Option<f32>,
+    pub rent_income_limit: Option<f32>,
+    pub strategy: Option<AcquisitionStrategy>,
+}
+
+// The DOMA a tenant contributes to: the one they hold the
+// most shares in, else the largest one focused on where they
+// live (or work, if homeless), else the largest citywide one
+pub fn choose_doma(domas: &[DOMA], tenant: &Tenant, city: &City) -> usize {
+    fn largest<'a>(acc: Option<&'a DOMA>, d: &'a DOMA) -> Option<&'a DOMA> {
+        match acc {
+            Some(best) if best.shares.len() >= d.shares.len() => Some(best),
+            _ => Some(d)
+        }
+    }
+    let held = domas.iter()
+        .filter(|d| d.active())
+        .filter_map(|d| d.shares.get(&tenant.id).map(|&s| (d.id, s)))
+        .fold(None, |acc: Option<(usize, f32)>, (d_id, s)| match acc {
+            Some(best) if best.1 >= s => Some(best),
+            _ => Some((d_id, s))
+        });
+    if let Some((d_id, _)) = held {
+        return d_id;
+    }
+    let pos = match tenant.unit {
+        Some(u_id) => city.units[u_id].pos,
+        None => tenant.work
+    };
+    let neighb = city.parcels.get(&pos).and_then(|p| p.neighborhood);
+    let active = domas.iter().filter(|d| d.active());
+    active.clone().filter(|d| !d.neighborhoods.is_empty() && d.covers(neighb)).fold(None, largest)
+        .or_else(|| active.clone().filter(|d| d.covers(neighb)).fold(None, largest))
+        .or_else(|| active.fold(None, largest))
+        .map(|d| d.id)
+        .unwrap()
 }
 
 impl DOMA {
-    pub fn new(funds: f32, p_rent_share: f32, p_reserves: f32, p_expenses: f32, rent_income_limit: Option<f32>) -> DOMA {
+    // Parameters the spec leaves out come from the config
+    pub fn new(id: usize, spec: &DOMASpec, conf: &Config) -> DOMA {
         DOMA {
-            funds: funds,
+            id,
+            neighborhoods: spec.neighborhoods.clone(),
+            merged_into: None,
+            funds: spec.starting_funds,
             raised: 0.,
-            shares: FnvHashMap::default(),
+            shares: BTreeMap::new(),
+            vesting: BTreeMap::new(),
             maintenance: 1.,
             units: Vec::new(),
-            p_rent_share: p_rent_share,
-            p_reserves: p_reserves,
-            p_expenses: p_expenses,
-            rent_income_limit: rent_income_limit
+            listed: Vec::new(),
+            strategy: spec.strategy.unwrap_or(conf.doma_acquisition_strategy),
+            rent_obvs: Vec::new(),
+            p_rent_share: spec.p_rent_share.unwrap_or(conf.doma_p_rent_share),
+            p_reserves: spec.p_reserves.unwrap_or(conf.doma_p_reserves),
+            p_expenses: conf.doma_p_expenses,
+            rent_income_limit: spec.rent_income_limit.or(conf.doma_rent_income_limit),
+            mortgages: Vec::new(),
+            bonds: Vec::new(),
+            debt_service: 0.,
+            months_negative: 0,
+        }
+    }
+
+    pub fn active(&self) -> bool {
+        self.merged_into.is_none()
+    }
+
+    // Whether it buys in the neighborhood
+    pub fn covers(&self, neighb: Option<usize>) -> bool {
+        match neighb {
+            Some(neighb_id) => self.neighborhoods.is_empty() || self.neighborhoods.contains(&neighb_id),
+            None => false
+        }
+    }
+
+    pub fn overlaps(&self, other: &DOMA) -> bool {
+        self.neighborhoods.is_empty() || other.neighborhoods.is_empty()
+            || self.neighborhoods.iter().any(|n| other.neighborhoods.contains(n))
+    }
+
+    // Take over another DOMA's units, funds,
+    // debts and members, and its focus.
+    // Its outstanding offers are withdrawn.
+    pub fn absorb(&mut self, other: &mut DOMA, city: &mut City, events: &mut EventLog) {
+        for unit in &mut city.units {
+            unit.offers.retain(|&(typ, id, _)| (typ, id) != (AgentType::DOMA, other.id));
+        }
+        for &u_id in &other.units {
+            city.units[u_id].owner = (AgentType::DOMA, self.id);
+        }
+        self.units.append(&mut other.units);
+        self.listed.append(&mut other.listed);
+        for (t_id, shares) in std::mem::take(&mut other.shares) {
+            *self.shares.entry(t_id).or_insert(0.) += shares;
         }
+        for (t_id, grants) in std::mem::take(&mut other.vesting) {
+            self.vesting.entry(t_id).or_default().extend(grants);
+        }
+        self.funds += other.funds;
+        self.raised += other.raised;
+        other.funds = 0.;
+        other.raised = 0.;
+        self.mortgages.append(&mut other.mortgages);
+        self.bonds.append(&mut other.bonds);
+        if other.neighborhoods.is_empty() {
+            self.neighborhoods.clear();
+        } else if !self.neighborhoods.is_empty() {
+            self.neighborhoods.extend(other.neighborhoods.iter());
+            self.neighborhoods.sort();
+            self.neighborhoods.dedup();
+        }
+        other.merged_into = Some(self.id);
+        events.emit(EventKind::DOMAMerged { doma: other.id, into: self.id });
+    }
+
+    pub fn debt(&self) -> f32 {
+        self.mortgages.iter().map(|m| m.balance).sum::<f32>()
+            + self.bonds.iter().map(|b| b.principal).sum::<f32>()
+    }
+
+    pub fn portfolio_value(&self, city: &City) -> f32 {
+        self.units.iter().map(|&u_id| city.units[u_id].value).sum()
+    }
+
+    // How much can be borrowed towards a unit at this
+    // price, given the down payment and leverage limit
+    fn loan_for(&self, price: f32, value: f32, debt: f32, economy: &Economy, conf: &Config) -> f32 {
+        let room = f32::max(0., conf.doma_max_ltv * (value + price) - debt);
+        f32::min(price * (1. - economy.down_payment(conf)), room)
+    }
+
+    // Pay for a purchased unit, borrowing what the leverage
+    // limit allows: first from members as bonds, then the
+    // rest as a mortgage at the going rate
+    #[allow(clippy::too_many_arguments)]
+    pub fn buy(&mut self, unit: usize, price: f32, city: &City, tenants: &mut [Tenant], economy: &Economy, conf: &Config, events: &mut EventLog) {
+        let loan = self.loan_for(price, self.portfolio_value(city), self.debt(), economy, conf);
+        self.units.push(unit);
+        self.funds -= price - loan;
+        if loan <= 0. {
+            return;
+        }
+        let raised = self.issue_bonds(loan, tenants, conf, events);
+        if loan > raised {
+            self.mortgages.push(Mortgage::new(unit, loan - raised, economy.interest_rate, conf.mortgage_term));
+        }
+    }
+
+    // Members lend part of their savings, in proportion
+    // to what they offer. Returns the amount raised.
+    fn issue_bonds(&mut self, amount: f32, tenants: &mut [Tenant], conf: &Config, events: &mut EventLog) -> f32 {
+        let offers: Vec<(usize, f32)> = self.shares.keys()
+            .filter(|&&t_id| !tenants[t_id].departed)
+            .map(|&t_id| (t_id, tenants[t_id].savings * conf.doma_bond_subscription))
+            .filter(|&(_, offer)| offer > 0.)
+            .collect();
+        let total: f32 = offers.iter().map(|&(_, offer)| offer).sum();
+        if total <= 0. {
+            return 0.;
+        }
+        let scale = f32::min(1., amount / total);
+        for (t_id, offer) in offers {
+            let principal = offer * scale;
+            tenants[t_id].savings -= principal;
+            self.bonds.push(Bond::new(t_id, principal, conf.doma_bond_rate, conf.doma_bond_term));
+            events.emit(EventKind::DOMABondIssued { doma: self.id, tenant: t_id, amount: principal });
+        }
+        f32::min(total, amount)
+    }
+
+    // Unit with the largest mortgage, if any
+    pub fn most_indebted_unit(&self) -> Option<usize> {
+        self.mortgages.iter()
+            .fold(None, |acc: Option<&Mortgage>, m| match acc {
+                Some(best) if best.balance >= m.balance => Some(best),
+                _ => Some(m)
+            })
+            .map(|m| m.unit)
+    }
+
+    // Lose a unit to the lender. The sale pays off as much
+    // of the mortgage as it can, the lender takes any shortfall.
+    pub fn foreclose(&mut self, unit: usize, price: f32) {
+        let balance = self.remove_mortgage(unit);
+        self.units.retain(|&u_id| u_id != unit);
+        self.listed.retain(|&u_id| u_id != unit);
+        self.funds += f32::max(0., price - balance);
+        self.months_negative = 0;
+    }
+
+    // The lender extends the unit's mortgage
+    // over a new term at the going rate, lowering payments
+    pub fn restructure(&mut self, unit: usize, economy: &Economy, conf: &Config) {
+        let balance = self.remove_mortgage(unit);
+        self.mortgages.push(Mortgage::new(unit, balance, economy.interest_rate, conf.mortgage_term));
+        self.months_negative = 0;
+    }
+
+    // Repay a holder's bonds early, as far as funds
+    // allow. Whatever can't be repaid is written off.
+    pub fn repay_bonds(&mut self, holder: usize, tenants: &mut [Tenant]) {
+        for bond in self.bonds.iter().filter(|b| b.holder == holder) {
+            let payment = f32::min(bond.principal, f32::max(self.funds, 0.));
+            self.funds -= payment;
+            tenants[holder].savings += payment;
+        }
+        self.bonds.retain(|b| b.holder != holder);
+    }
+
+    // Bondholders still in the city take
+    // shares for their bonds, which are written off
+    pub fn convert_bonds(&mut self, tenants: &[Tenant], events: &mut EventLog) {
+        for bond in self.bonds.drain(..) {
+            if tenants[bond.holder].departed {
+                continue;
+            }
+            *self.shares.entry(bond.holder).or_insert(0.) += bond.principal;
+            events.emit(EventKind::DOMABondConverted { doma: self.id, tenant: bond.holder, amount: bond.principal });
+        }
+        self.months_negative = 0;
+    }
+
+    fn remove_mortgage(&mut self, unit: usize) -> f32 {
+        let balance = self.mortgages.iter()
+            .filter(|m| m.unit == unit)
+            .map(|m| m.balance)
+            .sum();
+        self.mortgages.retain(|m| m.unit != unit);
+        balance
     }
 
-    pub fn step(&mut self, city: &mut City, tenants: &mut Vec<Tenant>, rng: &mut StdRng) {
-        // Mean income, for setting rent limit
-        let mean_income = tenants.iter().fold(0., |acc, t| acc + t.income)/tenants.len() as f32;
+    pub fn step(&mut self, city: &mut City, tenants: &mut [Tenant], economy: &Economy, rng: &mut StdRng, conf: &Config, events: &mut EventLog) {
+        // Mean income, for setting rent limit.
+        // Without any residents there's no limit.
+        let residents: Vec<&Tenant> = tenants.iter().filter(|t| !t.departed).collect();
         let rent_cap = match self.rent_income_limit {
-            Some(limit) => {
+            Some(limit) if !residents.is_empty() => {
+                let mean_income = residents.iter().fold(0., |acc, t| acc + t.income)/residents.len() as f32;
                 mean_income * limit
             },
-            None => {
+            _ => {
                 std::f32::INFINITY
             }
         };
 
-        // Collect rent
+        // Rent paid this month
         let mut rent = 0.;
         for &u_id in &self.units {
             let unit = &mut city.units[u_id];
@@ -464,111 +1018,257 @@ impl DOMA {
             unit.condition += self.maintenance;
             unit.condition = f32::min(f32::max(unit.condition, 0.), 1.);
 
-            if !unit.vacant() {
-                rent += unit.rent;
-                // let rent_per_tenant = rent / unit.tenants.len() as f32;
-                let rent_per_tenant = rent / unit.occupancy as f32;
-                for &t in &unit.tenants {
-                    let share = self.shares.entry(t).or_insert(0.);
-                    *share += rent_per_tenant * self.p_rent_share;
-                }
-            } else {
+            rent += unit.rent_paid;
+            if unit.vacant() {
                 continue;
             }
 
             // Adjust rents
-            unit.rent = f32::min(unit.rent, rent_cap);
+            if unit.rent > rent_cap {
+                events.emit(EventKind::RentChanged {
+                    unit: u_id, old_rent: unit.rent, new_rent: rent_cap, reason: RentChangeReason::DOMARentCap });
+                unit.rent = rent_cap;
+            }
         }
 
+        // Service debt before paying dividends. Bond
+        // payments go to the holders' savings.
+        let mut debt_service = 0.;
+        for mortgage in &mut self.mortgages {
+            debt_service += mortgage.pay();
+        }
+        self.mortgages.retain(|m| !m.paid_off());
+        for bond in &mut self.bonds {
+            let payment = bond.pay();
+            tenants[bond.holder].savings += payment;
+            debt_service += payment;
+        }
+        self.bonds.retain(|b| !b.matured());
+        self.debt_service = debt_service;
+        let net_rent = rent - debt_service;
+
         // Pay dividends
         let p_dividend = 1.0 - self.p_reserves - self.p_expenses;
-        let dividends = rent * p_dividend;
+        let dividends = f32::max(0., net_rent) * p_dividend;
         let total_shares: f32 = self.shares.values().sum();
         for (&tenant_id, share) in &self.shares {
             let tenant = &mut tenants[tenant_id];
             tenant.last_dividend = dividends * share/total_shares;
+            tenant.savings += tenant.last_dividend;
+            if tenant.last_dividend > 0. {
+                events.emit(EventKind::DOMADividend { doma: self.id, tenant: tenant_id, amount: tenant.last_dividend });
+            }
+        }
+        // Shortfalls come out of reserves
+        if net_rent > 0. {
+            self.funds += net_rent * self.p_reserves;
+        } else {
+            self.funds += net_rent;
+        }
+        if self.funds < 0. {
+            self.months_negative += 1;
+        } else {
+            self.months_negative = 0;
         }
-        self.funds += rent * self.p_reserves;
-
-        // TODO selling of properties
-
-        // // Make offers on properties
-        // // Get non-DOMA properties of DOMA tenants
-        // let mut candidates: Vec<(usize, f32, f32)> = self
-        //     .shares
-        //     .keys()
-        //     .filter_map(|&t| {
-        //         let tenant = &tenants[t];
-        //         match tenant.unit {
-        //             Some(u_id) => {
-        //                 let unit = &mut city.units[u_id];
-        //                 if unit.owner.0 != AgentType::DOMA {
-        //                     Some((u_id, unit.value, unit.rent))
-        //                 } else {
-        //                     None
-        //                 }
-        //             }
-        //             None => None,
-        //         }
-        //     })
-        //     .collect();
-
-        // // Otherwise, consider all unowned properties
-        // if candidates.len() == 0 {
-        //     candidates = city
-        //         .units
-        //         .iter_mut()
-        //         .filter_map(|unit| {
-        //             // Ensure unit is affordable
-        //             if unit.owner.0 != AgentType::DOMA {
-        //                 Some((unit.id, unit.value, unit.rent))
-        //             } else {
-        //                 None
-        //             }
-        //         })
-        //         .collect();
-        // }
-
-        let mut candidates: Vec<(usize, f32, f32)> = city
-            .units
-            .iter_mut()
-            .filter_map(|unit| {
-                // Ensure unit is affordable
-                if unit.owner.0 != AgentType::DOMA {
-                    Some((unit.id, unit.value, unit.rent))
-                } else {
-                    None
-                }
-            })
-            .collect();
-
 
-        // Filter to affordable
-        candidates = candidates
-            .into_iter()
-            .filter(|&(_, value, _)| value <= self.funds)
-            .collect();
+        let member_share = self.member_share(city, tenants);
+        self.list_units(city, &member_share, conf);
 
-        // Prioritize cheap properties with high rent-to-price ratios
-        candidates.sort_by_key(|&(_, value, rent)| (value * value / (rent + 1.)).round() as usize);
+        // Make offers in the order the strategy ranks units, up
+        // to the monthly cap and holding back the reserve floor.
+        // Whatever can be borrowed needn't come out of funds.
+        // Only buy where members live, so as not to buy what
+        // would be sold again, and never from other DOMAs.
+        self.observe_rents(city);
+        let context = self.acquisition_context(city, tenants, member_share);
+        let candidates: Vec<usize> = city.units.iter().filter(|unit| {
+            let neighb = city.parcels.get(&unit.pos).unwrap().neighborhood;
+            let near_members = match neighb {
+                Some(neighb_id) => context.member_share.is_empty()
+                    || context.member_share[neighb_id] >= conf.doma_min_member_share,
+                None => false
+            };
+            unit.owner.0 != AgentType::DOMA && near_members && self.covers(neighb)
+        }).map(|unit| unit.id).collect();
+        let ranked = self.strategy.rank(candidates, city, tenants, &context, conf);
 
-        // Make offers
+        let budget = self.funds - conf.doma_reserve_floor;
+        let mut value = self.portfolio_value(city);
+        let mut debt = self.debt();
         let mut committed = 0.;
-        for (id, value, _) in candidates {
-            let bid = value;
-            if (committed + bid) > self.funds {
+        let mut n_offers = 0;
+        for id in ranked {
+            if n_offers >= conf.doma_max_acquisitions {
                 break;
             }
-            committed += bid;
+            let bid = city.units[id].value * conf.doma_bid_markup;
+            let loan = self.loan_for(bid, value, debt, economy, conf);
+            if bid <= 0. || committed + bid - loan > budget {
+                continue;
+            }
+            committed += bid - loan;
+            value += bid;
+            debt += loan;
+            n_offers += 1;
             let unit = &mut city.units[id];
-            unit.offers.push((AgentType::DOMA, 0, bid));
+            unit.offers.push((AgentType::DOMA, self.id, bid));
+            events.emit(EventKind::OfferMade { unit: id, buyer: (AgentType::DOMA, self.id), amount: bid });
+        }
+    }
+
+    // Record each neighborhood's mean rent per area,
+    // keeping a year of observations
+    fn observe_rents(&mut self, city: &City) {
+        if self.rent_obvs.len() != city.neighborhoods.len() {
+            self.rent_obvs = vec![Vec::new(); city.neighborhoods.len()];
+        }
+        for (neighb_id, unit_ids) in city.units_by_neighborhood.iter().enumerate() {
+            if unit_ids.is_empty() {
+                continue;
+            }
+            let rent_per_area = unit_ids.iter()
+                .map(|&u_id| city.units[u_id].rent_per_area())
+                .sum::<f32>() / unit_ids.len() as f32;
+            let obvs = &mut self.rent_obvs[neighb_id];
+            obvs.push(rent_per_area);
+            if obvs.len() > 12 {
+                obvs.remove(0);
+            }
+        }
+    }
+
+    fn acquisition_context(&self, city: &City, tenants: &[Tenant], member_share: Vec<f32>) -> AcquisitionContext {
+        let n_neighborhoods = city.neighborhoods.len();
+        let mut holdings = vec![0.; n_neighborhoods];
+        for &u_id in &self.units {
+            if let Some(neighb_id) = city.parcels.get(&city.units[u_id].pos).unwrap().neighborhood {
+                holdings[neighb_id] += 1. / self.units.len() as f32;
+            }
         }
+        let rent_growth = self.rent_obvs.iter().map(|obvs| {
+            match (obvs.first(), obvs.last()) {
+                (Some(&first), Some(&last)) if first > 0. => last / first,
+                _ => 1.
+            }
+        }).collect();
+        let member_homes = self.shares.keys()
+            .filter_map(|&t_id| tenants[t_id].unit)
+            .collect();
+        AcquisitionContext {
+            member_share,
+            holdings,
+            rent_growth,
+            member_homes,
+        }
+    }
+
+    // Share of members, weighted by their shares, living in
+    // each neighborhood. Empty while there are no members living
+    // in the city.
+    fn member_share(&self, city: &City, tenants: &[Tenant]) -> Vec<f32> {
+        let mut member_share = vec![0.; city.neighborhoods.len()];
+        for (&t_id, &share) in &self.shares {
+            if let Some(u_id) = tenants[t_id].unit {
+                let parcel = city.parcels.get(&city.units[u_id].pos).unwrap();
+                if let Some(neighb_id) = parcel.neighborhood {
+                    member_share[neighb_id] += share;
+                }
+            }
+        }
+        let total: f32 = member_share.iter().sum();
+        if total <= 0. {
+            return Vec::new();
+        }
+        member_share.iter().map(|s| s / total).collect()
+    }
+
+    // Put units up for sale that are run down, far from where
+    // members live, or valuable enough that selling them would
+    // fund several cheaper ones
+    fn list_units(&mut self, city: &City, member_share: &[f32], conf: &Config) {
+        let mean_value = city.units.iter().map(|u| u.value).sum::<f32>() / city.units.len() as f32;
+        self.listed = self.units.iter().cloned().filter(|&u_id| {
+            let unit = &city.units[u_id];
+            let far_from_members = match city.parcels.get(&unit.pos).unwrap().neighborhood {
+                Some(neighb_id) => !member_share.is_empty() && member_share[neighb_id] < conf.doma_min_member_share,
+                None => true
+            };
+            unit.condition < conf.doma_sell_condition
+                || far_from_members
+                || unit.value > mean_value * conf.doma_sell_value_multiple
+        }).collect();
+    }
+
+    // Sell listed units for the best offer at or above
+    // the reserve price. Offers on other units are rejected.
+    pub fn check_purchase_offers(
+        &mut self,
+        city: &mut City,
+        conf: &Config,
+        events: &mut EventLog,
+    ) -> Vec<(AgentType, usize, usize, f32)> {
+        let mut transfers = Vec::new();
+        for &u in &self.units {
+            let unit = &mut city.units[u];
+            if unit.offers.is_empty() {
+                continue;
+            }
+            let reserve = if self.listed.contains(&u) {
+                unit.value * conf.doma_min_sale_price
+            } else {
+                f32::INFINITY
+            };
+            let (typ, buyer, best_amount): (AgentType, usize, f32) =
+                unit.offers.iter().fold(
+                    (AgentType::Landlord, 0, 0.),
+                    |(t, b, best), &(typ, buyer, amount)| {
+                        if amount >= reserve && amount > best {
+                            (typ, buyer, amount)
+                        } else {
+                            (t, b, best)
+                        }
+                    },
+                );
+            emit_offer_results(unit, (typ, buyer, best_amount), events);
+            if best_amount > 0. {
+                events.emit(EventKind::Sale {
+                    unit: u, seller: (AgentType::DOMA, self.id), buyer: (typ, buyer), price: best_amount });
+                unit.value = best_amount;
+                unit.owner = (typ, buyer);
+                transfers.push((typ, buyer, u, best_amount));
+            }
+            unit.offers.clear();
+        }
+
+        // Proceeds pay off the unit's mortgage
+        // and go back into the fund
+        for &(_, _, unit_id, price) in &transfers {
+            self.units.retain(|&u_id| u_id != unit_id);
+            self.listed.retain(|&u_id| u_id != unit_id);
+            self.funds += price - self.remove_mortgage(unit_id);
+        }
+        transfers
     }
 
-    pub fn add_funds(&mut self, tenant_id: usize, amount: f32) {
+    // Contributions come out of the tenant's
+    // savings, as much as they have
+    pub fn add_funds(&mut self, tenant: &mut Tenant, amount: f32, events: &mut EventLog) {
+        let amount = f32::min(amount, tenant.savings);
+        if amount <= 0. {
+            return;
+        }
+        tenant.savings -= amount;
+        events.emit(EventKind::DOMAContribution { doma: self.id, tenant: tenant.id, amount });
         self.funds += amount;
         self.raised += amount;
-        let share = self.shares.entry(tenant_id).or_insert(0.);
+        let share = self.shares.entry(tenant.id).or_insert(0.);
         *share += amount;
     }
+
+    // Part of the rent tenants pay
+    // the DOMA converts to shares
+    pub fn credit_rent(&mut self, tenant_id: usize, amount: f32, month: usize, conf: &Config) {
+        let shares = amount * self.p_rent_share;
+        self.credit_shares(tenant_id, shares, month, conf);
+    }
 }
@@ -1,15 +1,18 @@
 use rand::Rng;
 use std::cmp::{max, min};
-use std::str::FromStr;
-use super::design::{Design, Neighborhood};
+use super::design::{Design, Neighborhood, parse_parcel};
 use super::grid::{HexGrid, Position};
 use super::agent::{AgentType};
+use super::eviction::Eviction;
 use strum_macros::{EnumString, Display};
-use fnv::{FnvHashMap, FnvHashSet};
-use noise::{OpenSimplex, Seedable};
+use fnv::FnvHashMap;
+use noise::{NoiseFn, OpenSimplex, Seedable};
 use rand::rngs::StdRng;
 use rand_distr::{Beta, Distribution};
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeSet;
 
+#[derive(Serialize, Deserialize)]
 pub struct PositionVector<T: Clone> {
     dims: (isize, isize),
     data: Vec<Option<T>>
@@ -67,7 +70,7 @@ impl<T: Clone> PositionVector<T> {
     }
 }
 
-#[derive(Display, PartialEq, Debug, EnumString, Clone)]
+#[derive(Display, PartialEq, Debug, EnumString, Clone, Serialize, Deserialize)]
 pub enum ParcelType {
     Residential,
     Industrial,
@@ -75,7 +78,7 @@ pub enum ParcelType {
     River
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Parcel {
     pub typ: ParcelType,
     pub desirability: f32,
@@ -83,6 +86,7 @@ pub struct Parcel {
     pub pos: Position
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct City {
     pub grid: HexGrid,
     pub buildings: PositionVector<Building>,
@@ -92,6 +96,11 @@ pub struct City {
     pub residential_parcels_by_neighborhood: Vec<Vec<Position>>,
     pub commercial: PositionVector<usize>,
     pub neighborhoods: Vec<Neighborhood>,
+
+    // Noise can't be serialized,
+    // so trends are rebuilt from their seeds
+    pub neighborhood_seeds: Vec<u32>,
+    #[serde(skip)]
     pub neighborhood_trends: Vec<OpenSimplex>
 }
 
@@ -102,12 +111,16 @@ impl City {
         let cols = design.map.layout[0].len();
         let grid = HexGrid::new(rows, cols);
 
-        // Re-id neighborhoods so they are incremental values
+        // Re-id neighborhoods so they are incremental values,
+        // in order of their design ids so the result doesn't
+        // depend on the order they were loaded in
         let mut neighborhoods: Vec<Neighborhood> = Vec::new();
         let mut neighb_ids: FnvHashMap<usize, usize> = FnvHashMap::default();
-        for (i, (&id, neighb)) in design.neighborhoods.iter().enumerate() {
+        let mut design_ids: Vec<&usize> = design.neighborhoods.keys().collect();
+        design_ids.sort();
+        for (i, &id) in design_ids.into_iter().enumerate() {
             neighb_ids.insert(id, i);
-            neighborhoods.push(neighb.clone());
+            neighborhoods.push(design.neighborhoods[&id].clone());
         }
 
 
@@ -117,9 +130,7 @@ impl City {
             for (c, cell) in row.iter().enumerate() {
                 match cell {
                     Some(parcel_str) => {
-                        let parts: Vec<&str> = parcel_str.split("|").collect();
-                        let neighb_id: i32 = parts[0].parse().unwrap();
-                        let parcel_type = ParcelType::from_str(parts[1]).unwrap();
+                        let (neighb_id, parcel_type) = parse_parcel(parcel_str).unwrap();
                         let parcel = Parcel {
                             pos: (r as isize, c as isize),
                             typ: parcel_type,
@@ -155,11 +166,9 @@ impl City {
 
         // Group units by neighborhood for lookup
         // and create neighborhood desirability trends
-        let mut neighborhood_trends = Vec::new();
+        let mut neighborhood_seeds = Vec::new();
         for _ in neighb_ids.values() {
-            let mut noise = OpenSimplex::new();
-            noise = noise.set_seed(rng.gen());
-            neighborhood_trends.push(noise);
+            neighborhood_seeds.push(rng.gen());
             units_by_neighborhood.push(Vec::new());
             residential_parcels_by_neighborhood.push(Vec::new());
         }
@@ -178,7 +187,11 @@ impl City {
         }
         let nei_des_range = nei_des_max - nei_des_min;
         for n in &mut neighborhoods {
-            n.desirability = 1. + (n.desirability - nei_des_min)/(nei_des_range) - 0.5;
+            n.desirability = if nei_des_range > 0. {
+                1. + (n.desirability - nei_des_min)/(nei_des_range) - 0.5
+            } else {
+                1.
+            };
         }
 
         // Prepare buildings and units
@@ -186,7 +199,7 @@ impl City {
             match p.neighborhood {
                 Some(neighb_id) => {
                     let neighb = &neighborhoods[neighb_id];
-                    let mut n_units = rng.gen_range(neighb.min_units, neighb.max_units);
+                    let mut n_units = gen_range_or_min(rng, neighb.min_units, neighb.max_units);
                     let mut n_commercial = 0;
 
                     residential_parcels_by_neighborhood[neighb_id].push(p.pos);
@@ -205,7 +218,7 @@ impl City {
 
                     let mut building_units: Vec<usize> = Vec::new();
                     for _ in 0..n_units {
-                        let area = rng.gen_range(neighb.min_area, neighb.max_area) as f32;
+                        let area = gen_range_or_min(rng, neighb.min_area, neighb.max_area) as f32;
                         let value = design.city.price_per_sqm*area*neighb.desirability;
                         let rent = value/design.city.price_to_rent_ratio/12.;
                         // println!("value: {:?}, rent: {:?}", value, rent);
@@ -223,10 +236,13 @@ impl City {
                             area: area,
                             value: value,
                             condition: 1.0,
-                            tenants: FnvHashSet::default(),
+                            tenants: BTreeSet::new(),
                             offers: Vec::new(),
                             months_vacant: 0,
                             lease_month: 0,
+                            arrears: 0.,
+                            rent_paid: 0.,
+                            eviction: None,
                             recently_sold: false,
                             owner: (AgentType::Landlord, 0) // Dummy placeholder
                         };
@@ -300,10 +316,22 @@ impl City {
             neighborhoods: neighborhoods,
             units_by_neighborhood: units_by_neighborhood,
             residential_parcels_by_neighborhood: residential_parcels_by_neighborhood,
-            neighborhood_trends: neighborhood_trends,
+            neighborhood_trends: build_trends(&neighborhood_seeds),
+            neighborhood_seeds,
         }
     }
 
+    // Rebuild anything that isn't serialized
+    pub fn restore(&mut self) {
+        self.neighborhood_trends = build_trends(&self.neighborhood_seeds);
+    }
+
+    // Economic activity of a neighborhood, from -1 to 1;
+    // busier neighborhoods are more desirable
+    pub fn activity(&self, neighb_id: usize, month: usize, stretch_factor: f64) -> f32 {
+        -self.neighborhood_trends[neighb_id].get([month as f64 / stretch_factor, 0.]) as f32
+    }
+
     pub fn neighborhood_for_pos(&self, pos: &Position) -> Option<&Neighborhood> {
         let parcel = self.parcels.get(&pos).unwrap();
         match parcel.neighborhood {
@@ -315,16 +343,34 @@ impl City {
     }
 }
 
+fn build_trends(seeds: &[u32]) -> Vec<OpenSimplex> {
+    seeds.iter().map(|&seed| OpenSimplex::new().set_seed(seed)).collect()
+}
+
+// `gen_range` panics on an empty range,
+// so fixed ranges (min == max) just return min
+fn gen_range_or_min(rng: &mut StdRng, min: u32, max: u32) -> u32 {
+    if min < max {
+        rng.gen_range(min, max)
+    } else {
+        min
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct Unit {
     pub id: usize,
     pub rent: f32,
-    pub occupancy: usize,
+    pub occupancy: usize, // bedrooms
     pub condition: f32,
     pub area: f32,
     pub value: f32,
-    pub tenants: FnvHashSet<usize>,
+    pub tenants: BTreeSet<usize>, // the household living here
     pub months_vacant: usize,
     pub lease_month: usize,
+    pub arrears: f32, // rent owed by the household
+    pub rent_paid: f32, // this month
+    pub eviction: Option<Eviction>,
     pub owner: (AgentType, usize),
     pub pos: Position,
     pub recently_sold: bool,
@@ -336,10 +382,6 @@ impl Unit {
         self.tenants.len() == 0
     }
 
-    pub fn vacancies(&self) -> usize {
-        self.occupancy - self.tenants.len()
-    }
-
     pub fn rent_per_area(&self) -> f32 {
         self.rent/self.area
     }
@@ -354,7 +396,7 @@ impl Unit {
 }
 
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Building {
     pub units: Vec<usize>,
     pub n_commercial: usize
@@ -1,18 +1,46 @@
-use rand::Rng;
-use serde::Deserialize;
-use std::env;
+use super::acquisition::AcquisitionStrategy;
+use super::agent::DOMASpec;
+use super::governance::VotingRule;
+use serde::{Serialize, Deserialize};
+use serde_yaml::Value;
+use std::fmt;
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{self, BufReader};
+use std::path::Path;
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "UPPERCASE")]
 pub struct Config {
     pub design_id: String,
+
+    #[serde(default = "default_design_source")]
+    pub design_source: String,
+
     pub doma_starting_funds: f32,
     pub doma_p_rent_share: f32,
     pub doma_p_reserves: f32,
     pub doma_p_expenses: f32,
     pub doma_rent_income_limit: Option<f32>,
+    pub doma_sell_condition: f32,
+    pub doma_min_member_share: f32,
+    pub doma_sell_value_multiple: f32,
+    pub doma_min_sale_price: f32,
+    pub doma_acquisition_strategy: AcquisitionStrategy,
+    pub doma_bid_markup: f32,
+    pub doma_reserve_floor: f32,
+    pub doma_max_acquisitions: usize,
+    pub doma_vote_interval: usize,
+    pub doma_voting_rule: VotingRule,
+    pub doma_max_ltv: f32,
+    pub doma_bond_subscription: f32,
+    pub doma_bond_rate: f32,
+    pub doma_bond_term: usize,
+    pub domas: Vec<DOMASpec>,
+    pub doma_merge_min_members: usize,
+    pub doma_vesting_months: usize,
+    pub doma_redemption_share: f32,
+    pub doma_share_trading: bool,
+    pub doma_share_price_cap: Option<f32>,
     pub desirability_stretch_factor: f64,
     pub base_appreciation: f32,
     pub sample_size: usize,
@@ -20,7 +48,35 @@ pub struct Config {
     pub tenant_pool_size: usize,
     pub trend_months: usize,
     pub rent_increase_rate: f32,
+    pub landlord_p_aggressive: f32,
+    pub landlord_p_conservative: f32,
+    pub landlord_p_long_term_hold: f32,
+    pub landlord_starting_cash: f32,
+    pub landlord_initial_ltv: f32,
+    pub landlord_maintenance_cost: f32,
+    pub mortgage_rate: f32,
+    pub mortgage_term: usize,
+    pub rate_volatility: f32,
+    pub rate_schedule: Option<Vec<(usize, f32)>>,
+    pub inflation: f32,
+    pub inflation_volatility: f32,
+    pub inflation_schedule: Option<Vec<(usize, f32)>>,
+    pub credit_availability: f32,
+    pub credit_volatility: f32,
+    pub credit_schedule: Option<Vec<(usize, f32)>>,
+    pub macro_reversion: f32,
+    pub down_payment: f32,
+    pub foreclosure_months: usize,
+    pub foreclosure_discount: f32,
     pub moving_penalty: f32,
+    pub job_loss_rate: f32,
+    pub job_find_rate: f32,
+    pub commercial_sensitivity: f32,
+    pub wage_growth: f32,
+    pub wage_volatility: f32,
+    pub unemployment_benefit: f32,
+    pub retirement_income: f32,
+    pub retirement_age: usize,
     pub friend_limit: usize,
     pub transmission_rate: f32,
     pub encounter_rate: f32,
@@ -29,6 +85,39 @@ pub struct Config {
     pub burn_in: usize,
     pub max_contagion_depth: usize,
     pub pop_p_occupancy: f32,
+    pub household_p_single: f32,
+    pub household_p_family: f32,
+    pub household_p_roommates: f32,
+    pub partner_rate: f32,
+    pub family_split_rate: f32,
+    pub roommate_leave_rate: f32,
+    pub roommate_match_rate: f32,
+    pub mortality_base: f32,
+    pub mortality_growth: f32,
+    pub birth_rate: f32,
+    pub in_migration_rate: f32,
+    pub migration_job_sensitivity: f32,
+    pub out_migration_rate: f32,
+    pub cost_burden: f32,
+    pub homeless_months_to_leave: usize,
+    pub burdened_months_to_leave: usize,
+    pub rent_payment_capacity: f32,
+    pub starting_savings: f32,
+    pub home_search_rate: f32,
+    pub home_bid_premium: f32,
+    pub homeowner_maintenance_cost: f32,
+    pub homeowner_initial_ltv: f32,
+    pub eviction_arrears_months: f32,
+    pub nonpayment_notice_months: usize,
+    pub no_fault_notice_months: usize,
+    pub court_delay_months: usize,
+    pub counsel_delay_months: usize,
+    pub counsel_dismissal_rate: f32,
+    pub renovation_condition: f32,
+    pub renovation_cost: f32,
+    pub renovation_rate: f32,
+    pub just_cause: bool,
+    pub right_to_counsel: bool,
 
     #[serde(default)]
     pub steps: usize,
@@ -42,28 +131,96 @@ pub struct Config {
     pub sentry_dsn: String,
 }
 
-pub fn load_config() -> Config {
-    let file = File::open("config.yaml").expect("could not open file");
-    let reader = BufReader::new(file);
-    let mut conf: Config = serde_yaml::from_reader(reader).expect("error while reading yaml");
+// The shipped config. Keys missing from a config file,
+// e.g. ones added since it was written, take their values from it.
+const DEFAULT_CONFIG: &str = include_str!("../config.yaml");
+
+// Designs were originally only stored in redis
+fn default_design_source() -> String {
+    "redis://127.0.0.1/1".to_string()
+}
 
-    conf.steps = match env::var("STEPS") {
-        Ok(steps) => steps.parse().unwrap(),
-        Err(_) => 100,
-    };
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Yaml(serde_yaml::Error),
+    MalformedOverride(String),
+    InvalidOverride { key: String, err: serde_yaml::Error },
+    UnknownKey(String),
+}
 
-    conf.debug = match env::var("DEBUG") {
-        Ok(debug) => debug == "1",
-        Err(_) => false,
-    };
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "could not open config: {}", err),
+            ConfigError::Yaml(err) => write!(f, "could not read config: {}", err),
+            ConfigError::MalformedOverride(arg) =>
+                write!(f, "overrides must be of the form KEY=VALUE, got {:?}", arg),
+            ConfigError::InvalidOverride { key, err } => write!(f, "invalid value for {}: {}", key, err),
+            ConfigError::UnknownKey(key) => write!(f, "unknown config key {:?}", key),
+        }
+    }
+}
 
-    let mut rng = rand::thread_rng();
-    conf.seed = match env::var("SEED") {
-        Ok(seed) => seed.parse().unwrap(),
-        Err(_) => rng.gen(),
-    };
+impl From<io::Error> for ConfigError {
+    fn from(err: io::Error) -> ConfigError {
+        ConfigError::Io(err)
+    }
+}
 
-    println!("{:?}", conf);
+impl From<serde_yaml::Error> for ConfigError {
+    fn from(err: serde_yaml::Error) -> ConfigError {
+        ConfigError::Yaml(err)
+    }
+}
+
+// Load config from a YAML file, applying
+// `KEY=VALUE` overrides on top of it.
+// Values are parsed as YAML, so `null`, numbers, etc work as expected.
+pub fn load_config(path: &Path, overrides: &[(String, String)]) -> Result<Config, ConfigError> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+    let mut data: Value = serde_yaml::from_reader(reader)?;
+    if let (Some(mapping), Value::Mapping(defaults)) = (data.as_mapping_mut(), serde_yaml::from_str(DEFAULT_CONFIG)?) {
+        for (key, val) in defaults {
+            if !mapping.contains_key(&key) {
+                mapping.insert(key, val);
+            }
+        }
+    }
+    let conf: Config = serde_yaml::from_value(data)?;
+
+    // Overrides go on top of the loaded config rather than the file,
+    // so keys that have defaults and aren't in the file can be set too
+    let mut data = serde_yaml::to_value(&conf)?;
+    {
+        let mapping = data.as_mapping_mut().unwrap();
+        for (key, val) in overrides {
+            let key = key.to_uppercase();
+            let val: Value = serde_yaml::from_str(val)
+                .map_err(|err| ConfigError::InvalidOverride { key: key.clone(), err })?;
+            let key = Value::String(key);
+            if !mapping.contains_key(&key) {
+                return Err(ConfigError::UnknownKey(key.as_str().unwrap().to_string()));
+            }
+            mapping.insert(key, val);
+        }
+    }
+    Ok(serde_yaml::from_value(data)?)
+}
+
+pub fn save_config(conf: &Config, path: &Path) {
+    let data = serde_yaml::to_string(conf).unwrap();
+    std::fs::write(path, data).expect("Unable to write file");
+}
 
-    conf
+// Parse `KEY=VALUE` strings
+pub fn parse_overrides<'a, I: Iterator<Item=&'a str>>(args: I) -> Result<Vec<(String, String)>, ConfigError> {
+    args.map(|arg| {
+        let parts: Vec<&str> = arg.splitn(2, "=").collect();
+        if parts.len() != 2 {
+            return Err(ConfigError::MalformedOverride(arg.to_string()));
+        }
+        Ok((parts[0].to_string(), parts[1].to_string()))
+    }).collect()
 }
@@ -1,14 +1,188 @@
+use super::city::ParcelType;
+use super::generate::{generate, load_generator_config, GeneratorConfig, ParamsError};
 use fnv::FnvHashMap;
+#[cfg(feature = "server")]
 use redis::Commands;
 use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct Design {
     pub map: Map,
     pub neighborhoods: FnvHashMap<usize, Neighborhood>,
     pub city: CityConfig,
 }
 
+impl Design {
+    // Check the design for problems that would
+    // otherwise cause panics or silently bad cities
+    pub fn validate(&self) -> Vec<DesignError> {
+        let mut errors = Vec::new();
+
+        // Layout shape
+        let layout = &self.map.layout;
+        if layout.is_empty() || layout[0].is_empty() {
+            errors.push(DesignError::EmptyLayout);
+        } else {
+            let cols = layout[0].len();
+            for (r, row) in layout.iter().enumerate() {
+                if row.len() != cols {
+                    errors.push(DesignError::RaggedRow { row: r, len: row.len(), expected: cols });
+                }
+            }
+        }
+
+        // Neighborhood specifications
+        let mut keys: Vec<&usize> = self.neighborhoods.keys().collect();
+        keys.sort();
+        for &key in keys {
+            let neighb = &self.neighborhoods[&key];
+            if neighb.min_units == 0 || neighb.min_units > neighb.max_units {
+                errors.push(DesignError::InvalidUnitRange {
+                    neighborhood: key, min: neighb.min_units, max: neighb.max_units });
+            }
+            if neighb.min_area == 0 || neighb.min_area > neighb.max_area {
+                errors.push(DesignError::InvalidAreaRange {
+                    neighborhood: key, min: neighb.min_area, max: neighb.max_area });
+            }
+            if neighb.sqm_per_occupant == 0 {
+                errors.push(DesignError::InvalidSqmPerOccupant { neighborhood: key });
+            }
+            if !(neighb.p_commercial >= 0. && neighb.p_commercial < 1.) {
+                errors.push(DesignError::InvalidCommercialShare {
+                    neighborhood: key, p: neighb.p_commercial });
+            }
+        }
+
+        // Parcels
+        let mut can_have_commercial = false;
+        for (r, row) in layout.iter().enumerate() {
+            for (c, cell) in row.iter().enumerate() {
+                let cell = match cell {
+                    Some(cell) => cell,
+                    None => continue
+                };
+                match parse_parcel(cell) {
+                    Err(err) => errors.push(err.at(r, c)),
+                    Ok((-1, _)) => {},
+                    Ok((id, typ)) => {
+                        match self.neighborhoods.get(&(id as usize)) {
+                            Some(neighb) => {
+                                // Only buildings with more than 3 units get
+                                // commercial floors. Unit counts are random,
+                                // so only the minimum guarantees any.
+                                if typ == ParcelType::Residential
+                                    && neighb.p_commercial > 0. && neighb.min_units > 3 {
+                                    can_have_commercial = true;
+                                }
+                            },
+                            None => errors.push(DesignError::UnknownNeighborhood { row: r, col: c, id })
+                        }
+                    }
+                }
+            }
+        }
+
+        // Tenants need somewhere to work
+        if !can_have_commercial {
+            errors.push(DesignError::NoCommercial);
+        }
+
+        errors
+    }
+}
+
+// Parse a layout cell of the form "neighb_id|ParcelType"
+pub fn parse_parcel(cell: &str) -> Result<(i32, ParcelType), ParcelError> {
+    let parts: Vec<&str> = cell.split("|").collect();
+    if parts.len() != 2 {
+        return Err(ParcelError::Malformed(cell.to_string()));
+    }
+    let neighb_id: i32 = match parts[0].parse() {
+        Ok(id) if id >= -1 => id,
+        _ => return Err(ParcelError::InvalidNeighborhood(parts[0].to_string()))
+    };
+    let parcel_type = match ParcelType::from_str(parts[1]) {
+        Ok(typ) => typ,
+        Err(_) => return Err(ParcelError::UnknownType(parts[1].to_string()))
+    };
+    Ok((neighb_id, parcel_type))
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParcelError {
+    Malformed(String),
+    InvalidNeighborhood(String),
+    UnknownType(String),
+}
+
+impl ParcelError {
+    fn at(self, row: usize, col: usize) -> DesignError {
+        match self {
+            ParcelError::Malformed(cell) => DesignError::MalformedParcel { row, col, cell },
+            ParcelError::InvalidNeighborhood(id) => DesignError::InvalidNeighborhoodId { row, col, id },
+            ParcelError::UnknownType(typ) => DesignError::UnknownParcelType { row, col, typ },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum DesignError {
+    EmptyLayout,
+    RaggedRow { row: usize, len: usize, expected: usize },
+    MalformedParcel { row: usize, col: usize, cell: String },
+    InvalidNeighborhoodId { row: usize, col: usize, id: String },
+    UnknownParcelType { row: usize, col: usize, typ: String },
+    UnknownNeighborhood { row: usize, col: usize, id: i32 },
+    InvalidUnitRange { neighborhood: usize, min: u32, max: u32 },
+    InvalidAreaRange { neighborhood: usize, min: u32, max: u32 },
+    InvalidSqmPerOccupant { neighborhood: usize },
+    InvalidCommercialShare { neighborhood: usize, p: f32 },
+    NoCommercial,
+}
+
+impl DesignError {
+    // Unknown neighborhoods are tolerated by the city
+    // (the parcel gets no neighborhood), everything else
+    // would crash or corrupt the simulation
+    pub fn is_fatal(&self) -> bool {
+        !matches!(self, DesignError::UnknownNeighborhood { .. })
+    }
+}
+
+impl fmt::Display for DesignError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DesignError::EmptyLayout =>
+                write!(f, "map layout is empty"),
+            DesignError::RaggedRow { row, len, expected } =>
+                write!(f, "row {}: has {} cells, expected {}", row, len, expected),
+            DesignError::MalformedParcel { row, col, cell } =>
+                write!(f, "({}, {}): malformed parcel {:?}, expected \"neighb|Type\"", row, col, cell),
+            DesignError::InvalidNeighborhoodId { row, col, id } =>
+                write!(f, "({}, {}): invalid neighborhood id {:?}", row, col, id),
+            DesignError::UnknownParcelType { row, col, typ } =>
+                write!(f, "({}, {}): unknown parcel type {:?}", row, col, typ),
+            DesignError::UnknownNeighborhood { row, col, id } =>
+                write!(f, "({}, {}): neighborhood {} is not specified", row, col, id),
+            DesignError::InvalidUnitRange { neighborhood, min, max } =>
+                write!(f, "neighborhood {}: invalid unit range {}-{}", neighborhood, min, max),
+            DesignError::InvalidAreaRange { neighborhood, min, max } =>
+                write!(f, "neighborhood {}: invalid area range {}-{}", neighborhood, min, max),
+            DesignError::InvalidSqmPerOccupant { neighborhood } =>
+                write!(f, "neighborhood {}: sqm per occupant must be positive", neighborhood),
+            DesignError::InvalidCommercialShare { neighborhood, p } =>
+                write!(f, "neighborhood {}: commercial share {} must be in [0, 1)", neighborhood, p),
+            DesignError::NoCommercial =>
+                write!(f, "no residential parcel can have commercial floors, tenants will have nowhere to work"),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct Neighborhood {
@@ -24,7 +198,7 @@ pub struct Neighborhood {
     pub color: String
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct CityConfig {
     pub name: String,
@@ -37,7 +211,7 @@ pub struct CityConfig {
     pub income_sigma: f32,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct Map {
     pub layout: Vec<Vec<Option<String>>>,
     pub offset: MapOffset,
@@ -50,11 +224,111 @@ pub struct MapOffset {
 }
 
 
-pub fn load_design(design_id: &String) -> Design {
-    let client = redis::Client::open("redis://127.0.0.1/1").unwrap();
-    let con = client.get_connection().unwrap();
+// Where city designs are loaded from
+#[derive(Debug, Clone)]
+pub enum DesignSource {
+    File(PathBuf),      // a single JSON/YAML design file
+    Dir(PathBuf),       // a directory of `{design_id}.(json|yaml|yml)` files
+    Redis(String),      // a redis url, designs stored under `design:{design_id}`
+    Generated(Option<PathBuf>), // procedurally generated, using the design id as the seed
+}
+
+impl DesignSource {
+    // Redis urls are detected by their scheme and
+    // generated designs by "generate" or "generate:<params.yaml>",
+    // anything else is treated as a path
+    pub fn parse(source: &str) -> DesignSource {
+        if source.starts_with("redis://") {
+            DesignSource::Redis(source.to_string())
+        } else if source == "generate" {
+            DesignSource::Generated(None)
+        } else if let Some(path) = source.strip_prefix("generate:") {
+            DesignSource::Generated(Some(PathBuf::from(path)))
+        } else {
+            let path = PathBuf::from(source);
+            if path.is_dir() {
+                DesignSource::Dir(path)
+            } else {
+                DesignSource::File(path)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(PathBuf, io::Error),
+    Parse(String),
+    NotFound(String),
+    InvalidSeed(String),
+    Params(ParamsError),
+    Redis(String),
+    NoRedis,
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(path, err) => write!(f, "could not open {:?}: {}", path, err),
+            LoadError::Parse(err) => write!(f, "could not read design: {}", err),
+            LoadError::NotFound(design_id) => write!(f, "no design for id {:?}", design_id),
+            LoadError::InvalidSeed(design_id) =>
+                write!(f, "generated design ids must be integer seeds, got {:?}", design_id),
+            LoadError::Params(err) => write!(f, "{}", err),
+            LoadError::Redis(err) => write!(f, "redis error: {}", err),
+            LoadError::NoRedis => write!(f, "loading designs from redis requires the `server` feature"),
+        }
+    }
+}
+
+pub fn load_design(source: &DesignSource, design_id: &String) -> Result<Design, LoadError> {
+    match source {
+        DesignSource::File(path) => load_design_file(path),
+        DesignSource::Dir(dir) => {
+            let path = ["json", "yaml", "yml"].iter()
+                .map(|ext| dir.join(format!("{}.{}", design_id, ext)))
+                .find(|path| path.exists())
+                .ok_or_else(|| LoadError::NotFound(design_id.clone()))?;
+            load_design_file(&path)
+        },
+        #[cfg(feature = "server")]
+        DesignSource::Redis(url) => load_design_redis(url, design_id),
+        #[cfg(not(feature = "server"))]
+        DesignSource::Redis(_) => Err(LoadError::NoRedis),
+        DesignSource::Generated(params) => {
+            let seed: u64 = design_id.parse().map_err(|_| LoadError::InvalidSeed(design_id.clone()))?;
+            let conf = match params {
+                Some(path) => load_generator_config(path).map_err(LoadError::Params)?,
+                None => GeneratorConfig::default()
+            };
+            Ok(generate(seed, &conf))
+        }
+    }
+}
+
+pub fn save_design(design: &Design, path: &Path) {
+    let data = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("yaml") | Some("yml") => serde_yaml::to_string(design).unwrap(),
+        _ => serde_json::to_string(design).unwrap()
+    };
+    std::fs::write(path, data).expect("Unable to write file");
+}
+
+fn load_design_file(path: &Path) -> Result<Design, LoadError> {
+    let file = File::open(path).map_err(|err| LoadError::Io(path.to_path_buf(), err))?;
+    let reader = BufReader::new(file);
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("yaml") | Some("yml") => serde_yaml::from_reader(reader).map_err(|err| LoadError::Parse(err.to_string())),
+        _ => serde_json::from_reader(reader).map_err(|err| LoadError::Parse(err.to_string()))
+    }
+}
+
+#[cfg(feature = "server")]
+fn load_design_redis(url: &str, design_id: &String) -> Result<Design, LoadError> {
+    let client = redis::Client::open(url).map_err(|err| LoadError::Redis(err.to_string()))?;
+    let con = client.get_connection().map_err(|err| LoadError::Redis(err.to_string()))?;
     let design_key = format!("design:{}", design_id);
-    let design_data: String = con.get(design_key).expect("no design for that id");
-    let design: Design = serde_json::from_str(&design_data).expect("error while reading json");
-    design
+    let design_data: Option<String> = con.get(design_key).map_err(|err| LoadError::Redis(err.to_string()))?;
+    let design_data = design_data.ok_or_else(|| LoadError::NotFound(design_id.clone()))?;
+    serde_json::from_str(&design_data).map_err(|err| LoadError::Parse(err.to_string()))
 }
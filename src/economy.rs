@@ -0,0 +1,125 @@
+use super::config::Config;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use rand_distr::StandardNormal;
+use serde::{Serialize, Deserialize};
+
+// A macroeconomic variable. Follows its schedule if there is one,
+// otherwise it's a random walk pulled back towards its mean.
+struct Process<'a> {
+    mean: f32,
+    volatility: f32,
+    reversion: f32,
+    schedule: &'a Option<Vec<(usize, f32)>>,
+}
+
+impl<'a> Process<'a> {
+    fn next(&self, current: f32, month: usize, rng: &mut StdRng) -> f32 {
+        let shock: f32 = rng.sample(StandardNormal);
+        match self.schedule {
+            // Scheduled values hold until the next entry
+            Some(schedule) => schedule.iter()
+                .rfind(|(m, _)| *m <= month)
+                .map_or(self.mean, |&(_, val)| val),
+            None => current + self.reversion * (self.mean - current) + self.volatility * shock
+        }
+    }
+}
+
+// Interest rates, credit and inflation
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Economy {
+    // Annual rate on new mortgages
+    pub interest_rate: f32,
+
+    // Annual inflation
+    pub inflation: f32,
+
+    // How freely lenders lend, from 0 (cash only)
+    // to 1 (the standard down payment)
+    pub credit: f32,
+
+    // Cumulative inflation since the start
+    pub price_level: f32,
+
+    // Price to rent ratio the market
+    // supports at the current interest rate
+    pub price_to_rent_ratio: f32,
+
+    // Price to rent ratio at the
+    // config's long-run interest rate
+    base_price_to_rent_ratio: f32,
+
+    // Conditions at the last appraisal
+    appraised_price_level: f32,
+    appraised_price_to_rent_ratio: f32,
+}
+
+impl Economy {
+    pub fn new(price_to_rent_ratio: f32, conf: &Config) -> Economy {
+        Economy {
+            interest_rate: conf.mortgage_rate,
+            inflation: conf.inflation,
+            credit: conf.credit_availability,
+            price_level: 1.,
+            price_to_rent_ratio,
+            base_price_to_rent_ratio: price_to_rent_ratio,
+            appraised_price_level: 1.,
+            appraised_price_to_rent_ratio: price_to_rent_ratio,
+        }
+    }
+
+    pub fn step(&mut self, month: usize, rng: &mut StdRng, conf: &Config) {
+        let rate = Process {
+            mean: conf.mortgage_rate,
+            volatility: conf.rate_volatility,
+            reversion: conf.macro_reversion,
+            schedule: &conf.rate_schedule,
+        };
+        let inflation = Process {
+            mean: conf.inflation,
+            volatility: conf.inflation_volatility,
+            reversion: conf.macro_reversion,
+            schedule: &conf.inflation_schedule,
+        };
+        let credit = Process {
+            mean: conf.credit_availability,
+            volatility: conf.credit_volatility,
+            reversion: conf.macro_reversion,
+            schedule: &conf.credit_schedule,
+        };
+        self.interest_rate = f32::max(0., rate.next(self.interest_rate, month, rng));
+        self.inflation = inflation.next(self.inflation, month, rng);
+        self.credit = credit.next(self.credit, month, rng).clamp(0., 1.);
+        self.price_level *= 1. + self.inflation / 12.;
+
+        // Yields on property move with interest rates:
+        // at the long-run rate the market's ratio is the city's,
+        // and every point rates rise adds a point to the yield
+        let base_yield = 1. / self.base_price_to_rent_ratio;
+        let rental_yield = f32::max(MIN_YIELD, base_yield + self.interest_rate - conf.mortgage_rate);
+        self.price_to_rent_ratio = 1. / rental_yield;
+    }
+
+    // Share of a purchase price
+    // that has to be paid in cash
+    pub fn down_payment(&self, conf: &Config) -> f32 {
+        1. - (1. - conf.down_payment) * self.credit
+    }
+
+    // Change in values since the last appraisal. Values appreciate
+    // at the base rate, adjusted for unexpected inflation
+    // and changes in the price to rent ratio.
+    pub fn appreciation(&mut self, conf: &Config) -> f32 {
+        let inflation = self.price_level / self.appraised_price_level;
+        let expected_inflation = 1. + conf.inflation;
+        let ratio_change = self.price_to_rent_ratio / self.appraised_price_to_rent_ratio;
+        self.appraised_price_level = self.price_level;
+        self.appraised_price_to_rent_ratio = self.price_to_rent_ratio;
+        conf.base_appreciation * inflation / expected_inflation * ratio_change
+    }
+}
+
+// Keeps price to rent ratios finite
+// when interest rates collapse
+const MIN_YIELD: f32 = 0.01;
@@ -0,0 +1,358 @@
+use super::acquisition::AcquisitionStrategy;
+use super::agent::AgentType;
+use super::eviction::EvictionReason;
+use serde::{Serialize, Deserialize};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+// An agent, e.g. the buyer or seller of a unit
+pub type Agent = (AgentType, usize);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum RentChangeReason {
+    LeaseRenewal,
+    Vacancy,
+    DOMARentCap,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum DepartureReason {
+    Homelessness,
+    CostBurden,
+    Other,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum EventKind {
+    MovedIn { tenant: usize, unit: usize },
+    MovedOut { tenant: usize, unit: usize },
+
+    // Eviction cases against the unit's household. Filed cases are
+    // dismissed in court or withdrawn when tenants catch up on rent.
+    EvictionFiled { tenant: usize, unit: usize, reason: EvictionReason },
+    EvictionDismissed { tenant: usize, unit: usize, reason: EvictionReason },
+    Evicted { tenant: usize, unit: usize, reason: EvictionReason },
+
+    // Was housed at the start of the month but not at the end
+    BecameHomeless { tenant: usize },
+
+    OfferMade { unit: usize, buyer: Agent, amount: f32 },
+    OfferAccepted { unit: usize, buyer: Agent, amount: f32 },
+    OfferRejected { unit: usize, buyer: Agent, amount: f32 },
+    Sale { unit: usize, seller: Agent, buyer: Agent, price: f32 },
+
+    // Taken from an owner behind on their mortgage; without
+    // a buyer at auction the loan is restructured instead
+    Foreclosure { unit: usize, owner: Agent, buyer: Option<Agent>, price: f32 },
+    RentChanged { unit: usize, old_rent: f32, new_rent: f32, reason: RentChangeReason },
+    DOMAContribution { doma: usize, tenant: usize, amount: f32 },
+    DOMADividend { doma: usize, tenant: usize, amount: f32 },
+
+    // A DOMA taken over by another
+    DOMAMerged { doma: usize, into: usize },
+
+    // Members lending to DOMA, and swapping
+    // their bonds for shares when it defaults
+    DOMABondIssued { doma: usize, tenant: usize, amount: f32 },
+    DOMABondConverted { doma: usize, tenant: usize, amount: f32 },
+
+    // Members selling shares back to their
+    // DOMA, or to each other
+    DOMASharesRedeemed { doma: usize, tenant: usize, shares: f32, amount: f32 },
+    DOMASharesTraded { doma: usize, seller: usize, buyer: usize, shares: f32, price: f32 },
+
+    // Outcome of a members' vote on DOMA's parameters
+    DOMAVote { doma: usize, voters: usize, p_rent_share: f32, p_dividend: f32, rent_income_limit: Option<f32>, strategy: AcquisitionStrategy },
+
+    JoinedHousehold { tenant: usize, household: usize },
+    LeftHousehold { tenant: usize, household: usize },
+
+    Born { tenant: usize, household: usize },
+    Died { tenant: usize },
+    ArrivedInCity { tenant: usize },
+    LeftCity { tenant: usize, reason: DepartureReason },
+
+    JobLost { tenant: usize },
+    Hired { tenant: usize },
+    Retired { tenant: usize },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Event {
+    pub month: usize,
+
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+impl EventKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::MovedIn { .. } => "MovedIn",
+            EventKind::MovedOut { .. } => "MovedOut",
+            EventKind::EvictionFiled { .. } => "EvictionFiled",
+            EventKind::EvictionDismissed { .. } => "EvictionDismissed",
+            EventKind::Evicted { .. } => "Evicted",
+            EventKind::BecameHomeless { .. } => "BecameHomeless",
+            EventKind::OfferMade { .. } => "OfferMade",
+            EventKind::OfferAccepted { .. } => "OfferAccepted",
+            EventKind::OfferRejected { .. } => "OfferRejected",
+            EventKind::Sale { .. } => "Sale",
+            EventKind::Foreclosure { .. } => "Foreclosure",
+            EventKind::RentChanged { .. } => "RentChanged",
+            EventKind::DOMAContribution { .. } => "DOMAContribution",
+            EventKind::DOMADividend { .. } => "DOMADividend",
+            EventKind::DOMABondIssued { .. } => "DOMABondIssued",
+            EventKind::DOMABondConverted { .. } => "DOMABondConverted",
+            EventKind::DOMASharesRedeemed { .. } => "DOMASharesRedeemed",
+            EventKind::DOMASharesTraded { .. } => "DOMASharesTraded",
+            EventKind::DOMAVote { .. } => "DOMAVote",
+            EventKind::DOMAMerged { .. } => "DOMAMerged",
+            EventKind::JoinedHousehold { .. } => "JoinedHousehold",
+            EventKind::LeftHousehold { .. } => "LeftHousehold",
+            EventKind::Born { .. } => "Born",
+            EventKind::Died { .. } => "Died",
+            EventKind::ArrivedInCity { .. } => "ArrivedInCity",
+            EventKind::LeftCity { .. } => "LeftCity",
+            EventKind::JobLost { .. } => "JobLost",
+            EventKind::Hired { .. } => "Hired",
+            EventKind::Retired { .. } => "Retired",
+        }
+    }
+
+    pub fn tenant(&self) -> Option<usize> {
+        match *self {
+            EventKind::MovedIn { tenant, .. } |
+            EventKind::MovedOut { tenant, .. } |
+            EventKind::EvictionFiled { tenant, .. } |
+            EventKind::EvictionDismissed { tenant, .. } |
+            EventKind::Evicted { tenant, .. } |
+            EventKind::BecameHomeless { tenant } |
+            EventKind::DOMAContribution { tenant, .. } |
+            EventKind::DOMADividend { tenant, .. } |
+            EventKind::DOMABondIssued { tenant, .. } |
+            EventKind::DOMABondConverted { tenant, .. } |
+            EventKind::DOMASharesRedeemed { tenant, .. } |
+            EventKind::DOMASharesTraded { seller: tenant, .. } |
+            EventKind::JoinedHousehold { tenant, .. } |
+            EventKind::LeftHousehold { tenant, .. } |
+            EventKind::Born { tenant, .. } |
+            EventKind::Died { tenant } |
+            EventKind::ArrivedInCity { tenant } |
+            EventKind::LeftCity { tenant, .. } |
+            EventKind::JobLost { tenant } |
+            EventKind::Hired { tenant } |
+            EventKind::Retired { tenant } => Some(tenant),
+            _ => None
+        }
+    }
+
+    pub fn unit(&self) -> Option<usize> {
+        match *self {
+            EventKind::MovedIn { unit, .. } |
+            EventKind::MovedOut { unit, .. } |
+            EventKind::EvictionFiled { unit, .. } |
+            EventKind::EvictionDismissed { unit, .. } |
+            EventKind::Evicted { unit, .. } |
+            EventKind::OfferMade { unit, .. } |
+            EventKind::OfferAccepted { unit, .. } |
+            EventKind::OfferRejected { unit, .. } |
+            EventKind::Sale { unit, .. } |
+            EventKind::Foreclosure { unit, .. } |
+            EventKind::RentChanged { unit, .. } => Some(unit),
+            _ => None
+        }
+    }
+
+    pub fn doma(&self) -> Option<usize> {
+        match *self {
+            EventKind::DOMAContribution { doma, .. } |
+            EventKind::DOMADividend { doma, .. } |
+            EventKind::DOMABondIssued { doma, .. } |
+            EventKind::DOMABondConverted { doma, .. } |
+            EventKind::DOMASharesRedeemed { doma, .. } |
+            EventKind::DOMASharesTraded { doma, .. } |
+            EventKind::DOMAVote { doma, .. } => Some(doma),
+            _ => None
+        }
+    }
+
+    // Agents other than the tenant involved,
+    // i.e. buyers and sellers
+    pub fn agents(&self) -> Vec<Agent> {
+        match *self {
+            EventKind::OfferMade { buyer, .. } |
+            EventKind::OfferAccepted { buyer, .. } |
+            EventKind::OfferRejected { buyer, .. } => vec![buyer],
+            EventKind::Sale { seller, buyer, .. } => vec![seller, buyer],
+            EventKind::DOMAMerged { doma, into } => vec![(AgentType::DOMA, doma), (AgentType::DOMA, into)],
+            EventKind::DOMASharesTraded { doma, buyer, .. } => vec![(AgentType::Tenant, buyer), (AgentType::DOMA, doma)],
+            EventKind::Foreclosure { owner, buyer, .. } => match buyer {
+                Some(buyer) => vec![owner, buyer],
+                None => vec![owner]
+            },
+            _ => match self.doma() {
+                Some(doma) => vec![(AgentType::DOMA, doma)],
+                None => Vec::new()
+            }
+        }
+    }
+}
+
+// Events of the current month, including those
+// from player actions before the month's step.
+// Older events are dropped at the start of each step.
+#[derive(Default)]
+pub struct EventLog {
+    month: usize,
+    pub events: Vec<Event>,
+}
+
+impl EventLog {
+    // Month to record new events under
+    pub fn set_month(&mut self, month: usize) {
+        self.month = month;
+    }
+
+    pub fn drop_before(&mut self, month: usize) {
+        self.events.retain(|e| e.month >= month);
+    }
+
+    pub fn emit(&mut self, kind: EventKind) {
+        self.events.push(Event {
+            month: self.month,
+            kind,
+        });
+    }
+
+    pub fn count(&self, name: &str) -> usize {
+        self.events.iter().filter(|e| e.kind.name() == name).count()
+    }
+}
+
+// Filters for querying events; unset fields match everything
+#[derive(Default, Debug)]
+pub struct EventQuery {
+    pub types: Vec<String>,
+    pub tenant: Option<usize>,
+    pub unit: Option<usize>,
+    pub agent: Option<Agent>,
+    pub from: Option<usize>,
+    pub to: Option<usize>,
+}
+
+impl EventQuery {
+    pub fn matches(&self, event: &Event) -> bool {
+        (self.types.is_empty() || self.types.iter().any(|t| t == event.kind.name()))
+            && (self.tenant.is_none() || event.kind.tenant() == self.tenant)
+            && (self.unit.is_none() || event.kind.unit() == self.unit)
+            && (match self.agent {
+                Some(agent) => event.kind.agents().contains(&agent),
+                None => true
+            })
+            && (self.from.is_none() || Some(event.month) >= self.from)
+            && (self.to.is_none() || Some(event.month) <= self.to)
+    }
+}
+
+const CSV_HEADER: &str = "month,type,tenant,unit,buyer,seller,amount,old_amount,reason,doma";
+
+fn agent_to_string(agent: &Agent) -> String {
+    format!("{}:{}", agent.0, agent.1)
+}
+
+impl Event {
+    fn csv_row(&self) -> String {
+        let opt = |v: Option<usize>| v.map(|v| v.to_string()).unwrap_or_default();
+        let (buyer, seller, amount, old_amount, reason) = match &self.kind {
+            EventKind::OfferMade { buyer, amount, .. } |
+            EventKind::OfferAccepted { buyer, amount, .. } |
+            EventKind::OfferRejected { buyer, amount, .. } =>
+                (agent_to_string(buyer), String::new(), amount.to_string(), String::new(), String::new()),
+            EventKind::Sale { buyer, seller, price, .. } =>
+                (agent_to_string(buyer), agent_to_string(seller), price.to_string(), String::new(), String::new()),
+            EventKind::Foreclosure { owner, buyer, price, .. } =>
+                (buyer.as_ref().map(agent_to_string).unwrap_or_default(), agent_to_string(owner),
+                 price.to_string(), String::new(), String::new()),
+            EventKind::RentChanged { old_rent, new_rent, reason, .. } =>
+                (String::new(), String::new(), new_rent.to_string(), old_rent.to_string(), format!("{:?}", reason)),
+            EventKind::LeftCity { reason, .. } =>
+                (String::new(), String::new(), String::new(), String::new(), format!("{:?}", reason)),
+            EventKind::EvictionFiled { reason, .. } |
+            EventKind::EvictionDismissed { reason, .. } |
+            EventKind::Evicted { reason, .. } =>
+                (String::new(), String::new(), String::new(), String::new(), format!("{:?}", reason)),
+            EventKind::DOMAContribution { amount, .. } |
+            EventKind::DOMADividend { amount, .. } |
+            EventKind::DOMABondIssued { amount, .. } |
+            EventKind::DOMABondConverted { amount, .. } =>
+                (String::new(), String::new(), amount.to_string(), String::new(), String::new()),
+            EventKind::DOMASharesRedeemed { amount, .. } =>
+                (String::new(), String::new(), amount.to_string(), String::new(), String::new()),
+            EventKind::DOMASharesTraded { buyer, shares, price, .. } =>
+                (agent_to_string(&(AgentType::Tenant, *buyer)), String::new(),
+                 (shares * price).to_string(), String::new(), String::new()),
+            EventKind::DOMAMerged { doma, into } =>
+                (agent_to_string(&(AgentType::DOMA, *into)), agent_to_string(&(AgentType::DOMA, *doma)),
+                 String::new(), String::new(), String::new()),
+            EventKind::DOMAVote { p_dividend, strategy, .. } =>
+                (String::new(), String::new(), p_dividend.to_string(), String::new(), strategy.to_string()),
+            _ => (String::new(), String::new(), String::new(), String::new(), String::new())
+        };
+        format!("{},{},{},{},{},{},{},{},{},{}",
+                self.month, self.kind.name(), opt(self.kind.tenant()), opt(self.kind.unit()),
+                buyer, seller, amount, old_amount, reason, opt(self.kind.doma()))
+    }
+}
+
+// Appends events to a JSON Lines (`.jsonl`)
+// or CSV (`.csv`) file as a run goes
+pub struct EventWriter {
+    writer: BufWriter<File>,
+    csv: bool,
+}
+
+impl EventWriter {
+    pub fn create(path: &Path) -> EventWriter {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        let csv = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => true,
+            Some("jsonl") | Some("json") => false,
+            _ => panic!("event log must be .jsonl or .csv, got {:?}", path)
+        };
+        let mut writer = BufWriter::new(File::create(path).expect("could not create event log"));
+        if csv {
+            writeln!(writer, "{}", CSV_HEADER).unwrap();
+        }
+        EventWriter {
+            writer,
+            csv,
+        }
+    }
+
+    pub fn write(&mut self, events: &[Event]) {
+        for event in events {
+            if self.csv {
+                writeln!(self.writer, "{}", event.csv_row()).unwrap();
+            } else {
+                writeln!(self.writer, "{}", serde_json::to_string(event).unwrap()).unwrap();
+            }
+        }
+    }
+
+    pub fn flush(&mut self) {
+        self.writer.flush().unwrap();
+    }
+}
+
+// Read back a JSON Lines event log
+pub fn load_events(path: &Path) -> Vec<Event> {
+    let file = File::open(path).expect("could not open file");
+    BufReader::new(file).lines()
+        .map(|line| serde_json::from_str(&line.unwrap()).expect("error while reading event"))
+        .collect()
+}
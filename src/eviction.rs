@@ -0,0 +1,81 @@
+use super::config::Config;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use serde::{Serialize, Deserialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum EvictionReason {
+    // Too far behind on rent
+    Nonpayment,
+
+    // No-fault: the landlord wants the unit
+    // empty to sell it or to renovate it
+    Sale,
+    Renovation,
+}
+
+impl EvictionReason {
+    pub fn no_fault(&self) -> bool {
+        *self != EvictionReason::Nonpayment
+    }
+}
+
+// Tenant protections in force
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Protections {
+    // Only evictions for cause, i.e. no no-fault evictions
+    pub just_cause: bool,
+
+    // Tenants facing eviction have a lawyer
+    pub right_to_counsel: bool,
+}
+
+// An eviction case against a unit's household
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Eviction {
+    pub reason: EvictionReason,
+    pub filed: usize, // month
+
+    // Month the case is heard, after
+    // the notice period and court delays
+    pub hearing: usize,
+
+    // Whether the tenants have a lawyer
+    pub counsel: bool,
+}
+
+impl Eviction {
+    pub fn file(reason: EvictionReason, month: usize, protections: Protections, conf: &Config) -> Eviction {
+        let notice = match reason {
+            EvictionReason::Nonpayment => conf.nonpayment_notice_months,
+            _ => conf.no_fault_notice_months
+        };
+        let counsel = protections.right_to_counsel;
+
+        // Lawyers slow cases down
+        let delay = if counsel {
+            conf.court_delay_months + conf.counsel_delay_months
+        } else {
+            conf.court_delay_months
+        };
+        Eviction {
+            reason,
+            filed: month,
+            hearing: month + notice + delay,
+            counsel,
+        }
+    }
+
+    // Whether the court rules for the landlord
+    pub fn judgment(&self, protections: Protections, rng: &mut StdRng, conf: &Config) -> bool {
+        if self.reason.no_fault() && protections.just_cause {
+            return false;
+        }
+        if self.counsel {
+            let roll: f32 = rng.gen();
+            roll >= conf.counsel_dismissal_rate
+        } else {
+            true
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use serde::{Serialize, Deserialize};
+
+// A fixed-rate, fully amortizing loan on a unit
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Mortgage {
+    pub unit: usize,
+    pub balance: f32,
+    pub rate: f32, // annual
+    pub payment: f32, // monthly
+    pub months_left: usize,
+}
+
+impl Mortgage {
+    pub fn new(unit: usize, principal: f32, rate: f32, term: usize) -> Mortgage {
+        Mortgage {
+            unit,
+            balance: principal,
+            rate,
+            payment: Mortgage::payment(principal, rate, term),
+            months_left: term,
+        }
+    }
+
+    // Monthly payment that pays off the principal over the term
+    pub fn payment(principal: f32, rate: f32, term: usize) -> f32 {
+        if term == 0 {
+            return principal;
+        }
+        let r = rate / 12.;
+        if r == 0. {
+            principal / term as f32
+        } else {
+            principal * r / (1. - (1. + r).powi(-(term as i32)))
+        }
+    }
+
+    // Make this month's payment, returning the amount paid
+    pub fn pay(&mut self) -> f32 {
+        if self.months_left == 0 {
+            return 0.;
+        }
+        let interest = self.balance * self.rate / 12.;
+        let payment = if self.months_left == 1 {
+            self.balance + interest
+        } else {
+            f32::min(self.payment, self.balance + interest)
+        };
+        self.balance = f32::max(0., self.balance + interest - payment);
+        self.months_left -= 1;
+        payment
+    }
+
+    pub fn paid_off(&self) -> bool {
+        self.months_left == 0 || self.balance <= 0.
+    }
+}
+
+// An interest-only loan from a member,
+// repaid in full at the end of the term
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Bond {
+    pub holder: usize, // tenant id
+    pub principal: f32,
+    pub rate: f32, // annual
+    pub months_left: usize,
+}
+
+impl Bond {
+    pub fn new(holder: usize, principal: f32, rate: f32, term: usize) -> Bond {
+        Bond {
+            holder,
+            principal,
+            rate,
+            months_left: term,
+        }
+    }
+
+    // Make this month's payment, returning the amount paid
+    pub fn pay(&mut self) -> f32 {
+        if self.months_left == 0 {
+            return 0.;
+        }
+        self.months_left -= 1;
+        let interest = self.principal * self.rate / 12.;
+        if self.months_left == 0 {
+            interest + self.principal
+        } else {
+            interest
+        }
+    }
+
+    pub fn matured(&self) -> bool {
+        self.months_left == 0
+    }
+}
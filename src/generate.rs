@@ -0,0 +1,245 @@
+use super::design::{Design, Map, MapOffset, Neighborhood, CityConfig};
+use super::grid::{HexGrid, Position};
+use fnv::FnvHashMap;
+use rand::Rng;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::Deserialize;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "UPPERCASE", default)]
+pub struct GeneratorConfig {
+    pub rows: usize,
+    pub cols: usize,
+    pub neighborhoods: usize,
+
+    // Approximate share of parcels
+    // that are river, park, or industrial
+    pub river_density: f32,
+    pub park_density: f32,
+    pub industrial_density: f32,
+
+    // Share of parcels left empty,
+    // i.e. outside of the city
+    pub empty_density: f32,
+
+    // Monthly income distribution (lognormal)
+    pub income_mu: f32,
+    pub income_sigma: f32,
+
+    pub landlords: u32,
+    pub max_bedrooms: usize,
+    pub price_per_sqm: f32,
+    pub price_to_rent_ratio: f32,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> GeneratorConfig {
+        GeneratorConfig {
+            rows: 24,
+            cols: 24,
+            neighborhoods: 8,
+            river_density: 0.03,
+            park_density: 0.05,
+            industrial_density: 0.05,
+            empty_density: 0.,
+            income_mu: 8.,
+            income_sigma: 0.6,
+            landlords: 20,
+            max_bedrooms: 4,
+            price_per_sqm: 3000.,
+            price_to_rent_ratio: 15.,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ParamsError {
+    Io(io::Error),
+    Parse(serde_yaml::Error),
+    ZeroDimensions { rows: usize, cols: usize },
+}
+
+impl fmt::Display for ParamsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParamsError::Io(err) => write!(f, "could not open generator params: {}", err),
+            ParamsError::Parse(err) => write!(f, "could not read generator params: {}", err),
+            ParamsError::ZeroDimensions { rows, cols } =>
+                write!(f, "generated maps need at least one row and column, got {}x{}", rows, cols),
+        }
+    }
+}
+
+impl GeneratorConfig {
+    pub fn validate(&self) -> Result<(), ParamsError> {
+        if self.rows == 0 || self.cols == 0 {
+            return Err(ParamsError::ZeroDimensions { rows: self.rows, cols: self.cols });
+        }
+        Ok(())
+    }
+}
+
+pub fn load_generator_config(path: &Path) -> Result<GeneratorConfig, ParamsError> {
+    let file = File::open(path).map_err(ParamsError::Io)?;
+    let reader = BufReader::new(file);
+    let conf: GeneratorConfig = serde_yaml::from_reader(reader).map_err(ParamsError::Parse)?;
+    conf.validate()?;
+    Ok(conf)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Cell {
+    Empty,
+    Residential,
+    Industrial,
+    Park,
+    River
+}
+
+// Params must be valid, see `GeneratorConfig::validate`
+pub fn generate(seed: u64, conf: &GeneratorConfig) -> Design {
+    conf.validate().expect("invalid generator params");
+    let mut rng: StdRng = SeedableRng::seed_from_u64(seed);
+    let grid = HexGrid::new(conf.rows, conf.cols);
+
+    // Neighborhoods are the voronoi regions
+    // around randomly placed centers
+    let mut n_neighbs = usize::max(1, conf.neighborhoods);
+    let centers: Vec<Position> = (0..n_neighbs)
+        .map(|_| (rng.gen_range(0, conf.rows) as isize, rng.gen_range(0, conf.cols) as isize))
+        .collect();
+    let mut neighb_ids = vec![vec![0; conf.cols]; conf.rows];
+    for (r, row) in neighb_ids.iter_mut().enumerate() {
+        for (c, id) in row.iter_mut().enumerate() {
+            let pos = (r as isize, c as isize);
+            let (closest, _) = centers.iter().enumerate()
+                .map(|(i, &center)| (i, grid.distance(pos, center)))
+                .fold((0, 1./0.), |acc, (i, d)| if d < acc.1 { (i, d) } else { acc });
+            *id = closest;
+        }
+    }
+
+    // Centers can coincide or be crowded out by their neighbors,
+    // so drop neighborhoods without parcels. The first
+    // one always keeps its center, so keeps its id.
+    let mut n_parcels = vec![0; n_neighbs];
+    for row in &neighb_ids {
+        for &id in row {
+            n_parcels[id] += 1;
+        }
+    }
+    let mut new_ids = vec![0; n_neighbs];
+    let mut next_id = 0;
+    for id in 0..n_neighbs {
+        if n_parcels[id] > 0 {
+            new_ids[id] = next_id;
+            next_id += 1;
+        }
+    }
+    for row in neighb_ids.iter_mut() {
+        for id in row.iter_mut() {
+            *id = new_ids[*id];
+        }
+    }
+    n_neighbs = next_id;
+
+    let mut cells = vec![vec![Cell::Residential; conf.cols]; conf.rows];
+
+    // Rivers are random walks from the top to the bottom
+    // of the map, each covering roughly one parcel per row
+    let n_rivers = (conf.river_density * conf.cols as f32).ceil() as usize;
+    for _ in 0..n_rivers {
+        let mut c = rng.gen_range(0, conf.cols) as isize;
+        for row in cells.iter_mut() {
+            row[c as usize] = Cell::River;
+            c = isize::min(isize::max(c + rng.gen_range(-1, 2), 0), conf.cols as isize - 1);
+        }
+    }
+
+    for row in cells.iter_mut() {
+        for cell in row.iter_mut() {
+            if *cell == Cell::River {
+                continue;
+            }
+            let roll: f32 = rng.gen();
+            *cell = if roll < conf.empty_density {
+                Cell::Empty
+            } else if roll < conf.empty_density + conf.park_density {
+                Cell::Park
+            } else if roll < conf.empty_density + conf.park_density + conf.industrial_density {
+                Cell::Industrial
+            } else {
+                Cell::Residential
+            };
+        }
+    }
+
+    // Make sure the first neighborhood has
+    // at least one residential parcel
+    let (r, c) = centers[0];
+    cells[r as usize][c as usize] = Cell::Residential;
+
+    let layout = cells.iter().enumerate().map(|(r, row)| {
+        row.iter().enumerate().map(|(c, cell)| {
+            let typ = match cell {
+                Cell::Empty => return None,
+                Cell::Residential => "Residential",
+                Cell::Industrial => "Industrial",
+                Cell::Park => "Park",
+                Cell::River => "River",
+            };
+            Some(format!("{}|{}", neighb_ids[r][c], typ))
+        }).collect()
+    }).collect();
+
+    let mut neighborhoods = FnvHashMap::default();
+    for id in 0..n_neighbs {
+        // Denser neighborhoods have taller buildings
+        // and more commercial floors. The first neighborhood
+        // is always dense enough to have commercial space,
+        // so tenants have somewhere to work.
+        let density: f32 = if id == 0 { rng.gen_range(0.5, 1.) } else { rng.gen() };
+        let min_units = 1 + (density * 12.).round() as u32;
+        let max_units = min_units + 4 + (density * 24.).round() as u32;
+        let min_area = rng.gen_range(30, 60);
+        let max_area = min_area + rng.gen_range(30, 100);
+        neighborhoods.insert(id, Neighborhood {
+            id: id as isize,
+            name: format!("Neighborhood {}", id),
+            desirability: rng.gen_range(0., 10.),
+            min_units,
+            max_units,
+            min_area,
+            max_area,
+            sqm_per_occupant: rng.gen_range(15, 30),
+            p_commercial: 0.05 + density * 0.3,
+            color: format!("#{:06x}", rng.gen_range(0, 0xffffff)),
+        });
+    }
+
+    Design {
+        map: Map {
+            layout,
+            offset: MapOffset {
+                row: false,
+                col: false,
+            },
+        },
+        neighborhoods,
+        city: CityConfig {
+            name: format!("Generated {}", seed),
+            max_bedrooms: conf.max_bedrooms,
+            price_per_sqm: conf.price_per_sqm,
+            price_to_rent_ratio: conf.price_to_rent_ratio,
+            landlords: conf.landlords,
+            population: 0,
+            income_mu: conf.income_mu,
+            income_sigma: conf.income_sigma,
+        },
+    }
+}
@@ -0,0 +1,164 @@
+use super::acquisition::AcquisitionStrategy;
+use super::agent::{AgentType, Tenant, DOMA};
+use super::city::City;
+use super::config::Config;
+use super::events::{EventKind, EventLog};
+use serde::{Serialize, Deserialize};
+use strum_macros::{Display, EnumString};
+
+// How members' votes are counted
+#[derive(Display, EnumString, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum VotingRule {
+    // Votes count in proportion to shares
+    ShareWeighted,
+
+    // Every member's vote counts the same
+    OneMemberOneVote,
+
+    // Votes count in proportion to
+    // the square root of shares
+    Quadratic,
+}
+
+impl VotingRule {
+    fn weight(&self, shares: f32) -> f32 {
+        match self {
+            VotingRule::ShareWeighted => shares,
+            VotingRule::OneMemberOneVote => 1.,
+            VotingRule::Quadratic => shares.sqrt(),
+        }
+    }
+}
+
+// A member's preferred DOMA parameters
+struct Ballot {
+    weight: f32,
+    p_rent_share: f32,
+    p_dividend: f32,
+
+    // Infinite for no limit
+    rent_income_limit: f32,
+    strategy: AcquisitionStrategy,
+}
+
+const STRATEGIES: [AcquisitionStrategy; 4] = [
+    AcquisitionStrategy::MemberFirst,
+    AcquisitionStrategy::AntiDisplacement,
+    AcquisitionStrategy::NeighborhoodConcentration,
+    AcquisitionStrategy::YieldMaximizing,
+];
+
+// Value that at least half the voting
+// weight is at or below
+fn weighted_median(mut votes: Vec<(f32, f32)>) -> f32 {
+    votes.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    let total: f32 = votes.iter().map(|&(_, w)| w).sum();
+    let mut acc = 0.;
+    for &(value, weight) in &votes {
+        acc += weight;
+        if acc >= total/2. {
+            return value;
+        }
+    }
+    votes.last().unwrap().0
+}
+
+impl DOMA {
+    // What a member wants, given their circumstances
+    fn ballot(&self, tenant: &Tenant, shares: f32, city: &City, mean_income: f32, conf: &Config) -> Ballot {
+        let burden = if tenant.income > 0. { tenant.last_rent / tenant.income } else { 1. };
+        let dividend_ratio = if tenant.income > 0. { tenant.last_dividend / tenant.income } else { 1. };
+        let unit = tenant.unit.map(|u_id| &city.units[u_id]);
+        let doma_tenant = match unit {
+            Some(unit) => unit.owner == (AgentType::DOMA, self.id),
+            None => false
+        };
+        let struggling = burden > conf.cost_burden || match unit {
+            Some(unit) => unit.arrears > 0. || unit.eviction.is_some(),
+            None => true
+        };
+
+        // Members want rent to earn shares the more of their income
+        // rent takes, and less the more they rely on dividends,
+        // which rent paid as shares dilutes
+        let p_rent_share = f32::min(1., burden / conf.cost_burden)
+            * conf.cost_burden / (conf.cost_burden + dividend_ratio);
+
+        // Members who are burdened or rely on dividends want
+        // them paid out; the rest want reserves for buying units
+        let need = burden + dividend_ratio;
+        let p_dividend = (1. - self.p_expenses) * need / (need + conf.cost_burden);
+
+        // Members want rents they could afford,
+        // raised by how much they rely on the rent income
+        let rent_income_limit = if mean_income > 0. {
+            conf.cost_burden * tenant.income / mean_income
+                * (1. + dividend_ratio / conf.cost_burden)
+        } else {
+            f32::INFINITY
+        };
+
+        let strategy = if struggling {
+            AcquisitionStrategy::AntiDisplacement
+        } else if doma_tenant {
+            AcquisitionStrategy::NeighborhoodConcentration
+        } else if unit.is_some() && !tenant.units.contains(&unit.unwrap().id) {
+            AcquisitionStrategy::MemberFirst
+        } else {
+            AcquisitionStrategy::YieldMaximizing
+        };
+
+        Ballot {
+            weight: conf.doma_voting_rule.weight(shares),
+            p_rent_share,
+            p_dividend,
+            rent_income_limit,
+            strategy,
+        }
+    }
+
+    // Members vote on rent shares, dividends, the rent limit and
+    // what to buy. Rates go to the weighted median vote and the
+    // acquisition strategy to the plurality.
+    pub fn hold_vote(&mut self, city: &City, tenants: &[Tenant], conf: &Config, events: &mut EventLog) {
+        let residents: Vec<&Tenant> = tenants.iter().filter(|t| !t.departed).collect();
+        let mean_income = residents.iter().fold(0., |acc, t| acc + t.income)/residents.len() as f32;
+        let ballots: Vec<Ballot> = self.shares.iter()
+            .filter(|&(&t_id, &shares)| shares > 0. && !tenants[t_id].departed)
+            .map(|(&t_id, &shares)| self.ballot(&tenants[t_id], shares, city, mean_income, conf))
+            .filter(|ballot| ballot.weight > 0.)
+            .collect();
+        if ballots.is_empty() {
+            return;
+        }
+
+        let p_rent_share = weighted_median(ballots.iter().map(|b| (b.p_rent_share, b.weight)).collect());
+        let p_dividend = weighted_median(ballots.iter().map(|b| (b.p_dividend, b.weight)).collect());
+        let rent_income_limit = weighted_median(ballots.iter().map(|b| (b.rent_income_limit, b.weight)).collect());
+        let strategy = STRATEGIES.iter().map(|&strategy| {
+            let votes: f32 = ballots.iter()
+                .filter(|b| b.strategy == strategy)
+                .map(|b| b.weight).sum();
+            (strategy, votes)
+        }).fold((self.strategy, 0.), |best, (strategy, votes)| {
+            if votes > best.1 { (strategy, votes) } else { best }
+        }).0;
+
+        self.p_rent_share = p_rent_share;
+        self.p_reserves = 1. - p_dividend - self.p_expenses;
+        self.rent_income_limit = if rent_income_limit.is_finite() {
+            Some(rent_income_limit)
+        } else {
+            None
+        };
+        self.strategy = strategy;
+        events.emit(EventKind::DOMAVote {
+            doma: self.id,
+            voters: ballots.len(),
+            p_rent_share: self.p_rent_share,
+            p_dividend,
+            rent_income_limit: self.rent_income_limit,
+            strategy,
+        });
+    }
+}
@@ -1,3 +1,4 @@
+use serde::{Serialize, Deserialize};
 use std::collections::HashSet;
 
 pub type Position = (isize, isize);
@@ -20,6 +21,7 @@ const EVEN_ADJACENT_POSITIONS: [(isize, isize); 6] = [
     (1, 0),   // bottom right
 ];
 
+#[derive(Serialize, Deserialize)]
 pub struct HexGrid {
     pub rows: usize,
     pub cols: usize,
@@ -0,0 +1,637 @@
+use super::agent::{distance, AgentType, Employment, Tenant};
+use super::city::{City, Parcel, Unit};
+use super::config::Config;
+use super::economy::Economy;
+use super::finance::Mortgage;
+use super::events::{EventKind, EventLog};
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use serde::{Serialize, Deserialize};
+use std::cmp::max;
+use strum_macros::Display;
+
+#[derive(Display, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum HouseholdKind {
+    Single,
+
+    // A couple and any children
+    Family,
+
+    // Unrelated adults sharing a unit
+    Roommates,
+}
+
+// Tenants who live together, pool their
+// incomes and decide together where to live
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Household {
+    pub id: usize,
+    pub kind: HouseholdKind,
+    pub members: Vec<usize>,
+    pub unit: Option<usize>,
+
+    // Consecutive months without a home,
+    // and paying more than `COST_BURDEN` of income on rent
+    pub months_homeless: usize,
+    pub months_burdened: usize,
+}
+
+// What a household decided to do this month.
+// Decisions are made in parallel against the city
+// as it was at the start of the household phase,
+// then applied one household at a time.
+#[derive(Debug)]
+pub struct HouseholdDecision {
+    // If looking for a place, how desirable it must be to move
+    pub min_desirability: Option<f32>,
+
+    // Sampled units worth moving into,
+    // with their desirability, best first
+    pub move_to: Vec<(usize, f32)>,
+
+    // Offer on a unit to buy and live in, if any
+    pub offer: Option<(usize, f32)>,
+}
+
+impl Household {
+    pub fn new(id: usize, kind: HouseholdKind, members: Vec<usize>) -> Household {
+        Household {
+            id,
+            kind,
+            members,
+            unit: None,
+            months_homeless: 0,
+            months_burdened: 0,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.members.len()
+    }
+
+    // Households whose members have all
+    // left or joined other households
+    pub fn dissolved(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    pub fn player(&self, tenants: &[Tenant]) -> bool {
+        self.members.iter().any(|&t_id| tenants[t_id].player)
+    }
+
+    pub fn income(&self, tenants: &[Tenant]) -> f32 {
+        self.members.iter().map(|&t_id| tenants[t_id].income).sum()
+    }
+
+    pub fn dividends(&self, tenants: &[Tenant]) -> f32 {
+        self.members.iter().map(|&t_id| tenants[t_id].last_dividend).sum()
+    }
+
+    pub fn savings(&self, tenants: &[Tenant]) -> f32 {
+        self.members.iter().map(|&t_id| tenants[t_id].savings).sum()
+    }
+
+    // Member who holds the title to units the household
+    // buys, i.e. the one with the highest income
+    pub fn head(&self, tenants: &[Tenant]) -> Option<usize> {
+        self.members.iter().cloned().fold(None, |acc: Option<usize>, t_id| match acc {
+            Some(best) if tenants[best].income >= tenants[t_id].income => Some(best),
+            _ => Some(t_id)
+        })
+    }
+
+    // Whether a member owns the unit the household lives in
+    pub fn owns_home(&self, city: &City) -> bool {
+        match self.unit {
+            Some(u_id) => match city.units[u_id].owner {
+                (AgentType::Tenant, t_id) => self.members.contains(&t_id),
+                _ => false
+            },
+            None => false
+        }
+    }
+
+    // Owner-occupiers' monthly costs: mortgage and upkeep
+    pub fn ownership_costs(&self, unit: &Unit, tenants: &[Tenant], conf: &Config) -> f32 {
+        let payments: f32 = self.members.iter()
+            .flat_map(|&t_id| tenants[t_id].mortgages.iter())
+            .filter(|m| m.unit == unit.id)
+            .map(|m| m.payment)
+            .sum();
+        payments + unit.value * conf.homeowner_maintenance_cost / 12.
+    }
+
+    // The most a household could pay for a unit, given the
+    // down payment out of savings and that the mortgage and
+    // upkeep can take at most a third of income
+    pub fn max_price(&self, tenants: &[Tenant], economy: &Economy, conf: &Config) -> f32 {
+        let down_payment = economy.down_payment(conf);
+        let cost_per_dollar = Mortgage::payment(1. - down_payment, economy.interest_rate, conf.mortgage_term)
+            + conf.homeowner_maintenance_cost / 12.;
+        f32::min(
+            self.savings(tenants) / down_payment,
+            self.income(tenants) / 3. / cost_per_dollar)
+    }
+
+    // Couples share a bedroom,
+    // everyone else needs their own
+    pub fn bedrooms_needed(&self) -> usize {
+        match self.kind {
+            HouseholdKind::Family => max(1, self.size() - 1),
+            _ => self.size()
+        }
+    }
+
+    pub fn fits(&self, unit: &Unit) -> bool {
+        self.bedrooms_needed() <= unit.occupancy
+    }
+
+    // Rent after the members' DOMA dividends
+    pub fn adjusted_rent(&self, unit: &Unit, tenants: &[Tenant]) -> f32 {
+        let rent = f32::max(1., unit.rent);
+        rent - f32::min(rent, self.dividends(tenants))
+    }
+
+    pub fn desirability(&self, unit: &Unit, parcel: &Parcel, tenants: &[Tenant]) -> f32 {
+        if !self.fits(unit) {
+            return 0.;
+        }
+        let income = self.income(tenants);
+        let adjusted_rent = self.adjusted_rent(unit, tenants);
+
+        // Households can spend at most 1/3 of income on rent
+        if income/3. < adjusted_rent {
+            0.
+        } else {
+            let ratio = (income / adjusted_rent).sqrt();
+            let spaciousness = f32::max(unit.area / self.size() as f32, 0.).powf(1. / 32.);
+
+            // Members' mean commute
+            let commute = self.members.iter().map(|&t_id| {
+                let commute_distance = distance(tenants[t_id].work, unit.pos);
+                if commute_distance == 0. {
+                    1.
+                } else {
+                    1. / commute_distance
+                }
+            }).sum::<f32>() / self.size() as f32;
+            ratio * (spaciousness + parcel.desirability + unit.condition + commute)
+        }
+    }
+
+    // How desirable a vacant unit is to move into
+    fn vacancy_desirability(&self, unit: &Unit, city: &City, tenants: &[Tenant], conf: &Config) -> f32 {
+        if conf.debug && unit.is_doma() {
+            // If playing (i.e. debug=false), bots
+            // leave DOMA units for players to choose from
+            0.
+        } else {
+            let parcel = &city.parcels.get(&unit.pos).unwrap();
+            self.desirability(unit, parcel, tenants)
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn decide(
+        &self,
+        tenants: &[Tenant],
+        city: &City,
+        economy: &Economy,
+        month: usize,
+        vacant_units: &[usize],
+        rng: &mut StdRng,
+        conf: &Config,
+    ) -> HouseholdDecision {
+        let owner = self.owns_home(city);
+        let mut reconsider;
+        let mut current_desirability = 0.;
+        let mut moving_penalty = conf.moving_penalty;
+
+        match self.unit {
+            // If currently w/o home,
+            // will always look for a place to move into,
+            // with no moving penalty
+            None => {
+                reconsider = true;
+                current_desirability = -1.;
+                moving_penalty = 0.;
+            }
+
+            // Otherwise, only consider moving
+            // between leases, if the household has
+            // outgrown their place, or if their current
+            // place is no longer affordable. Households that
+            // can't find anywhere cheaper stay and fall behind on rent.
+            // Owner-occupiers have no lease and only move if they
+            // outgrow their home.
+            Some(u_id) if owner => {
+                reconsider = !self.fits(&city.units[u_id]);
+                moving_penalty = 0.;
+            }
+            Some(u_id) => {
+                let unit = &city.units[u_id];
+                let elapsed = month.saturating_sub(unit.lease_month);
+                reconsider = elapsed > 0 && elapsed.is_multiple_of(12);
+                if !self.fits(unit) {
+                    reconsider = true;
+                    moving_penalty = 0.;
+                } else if !reconsider {
+                    // No longer can afford
+                    let parcel = &city.parcels.get(&unit.pos).unwrap();
+                    current_desirability = self.desirability(unit, parcel, tenants);
+                    if current_desirability == 0. {
+                        reconsider = true;
+                        moving_penalty = 0.;
+                    }
+                }
+            }
+        }
+
+        // Others may take sampled units first, so keep every one worth
+        // moving into as a fallback. Ties keep their sampled order.
+        let mut move_to = Vec::new();
+        let mut min_desirability = None;
+        if reconsider {
+            let min = f32::max(0., current_desirability + moving_penalty);
+            min_desirability = Some(min);
+            if !vacant_units.is_empty() {
+                let sample = vacant_units.choose_multiple(rng, conf.tenant_sample_size);
+                for &u_id in sample {
+                    let u = &city.units[u_id];
+                    if u.vacant() {
+                        let desirability = self.vacancy_desirability(u, city, tenants, conf);
+                        if desirability > min {
+                            move_to.push((u_id, desirability));
+                        }
+                    }
+                }
+                move_to.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+            }
+        }
+
+        // Renters with enough saved look for a home to buy,
+        // bidding up to a premium over the listed value
+        let mut offer = None;
+        let roll: f32 = rng.gen();
+        if !owner && roll < conf.home_search_rate && !vacant_units.is_empty() {
+            let max_price = self.max_price(tenants, economy, conf);
+            let sample = vacant_units.choose_multiple(rng, conf.tenant_sample_size);
+            let best = sample.fold(None, |acc: Option<(usize, f32)>, &u_id| {
+                let u = &city.units[u_id];
+                let owned = match u.owner {
+                    (AgentType::Tenant, t_id) => self.members.contains(&t_id),
+                    _ => false
+                };
+                if owned || u.value <= 0. || u.value > max_price {
+                    return acc;
+                }
+                let p = &city.parcels.get(&u.pos).unwrap();
+                let desirability = self.desirability(u, p, tenants);
+                match acc {
+                    Some((_, best)) if best >= desirability => acc,
+                    _ if desirability > 0. => Some((u_id, desirability)),
+                    _ => acc
+                }
+            });
+            offer = best.map(|(u_id, _)| {
+                let value = city.units[u_id].value;
+                (u_id, f32::min(max_price, value * (1. + conf.home_bid_premium)))
+            });
+        }
+
+        HouseholdDecision {
+            min_desirability,
+            move_to,
+            offer,
+        }
+    }
+
+    // Carry out a decision. Someone else may have taken the chosen
+    // place since deciding, so this falls back to the next best
+    // one that's still vacant, and also considers units
+    // vacated this month, after decisions were made.
+    #[allow(clippy::too_many_arguments)]
+    pub fn act(&mut self, decision: &HouseholdDecision, tenants: &mut [Tenant], city: &mut City, month: usize, vacated: &mut Vec<usize>, conf: &Config, events: &mut EventLog) {
+        if let Some(min_desirability) = decision.min_desirability {
+            let best = decision.move_to.iter()
+                .find(|&&(u_id, _)| city.units[u_id].vacant())
+                .cloned();
+            let best = vacated.iter()
+                .filter(|&&u_id| city.units[u_id].vacant() && Some(u_id) != self.unit)
+                .map(|&u_id| (u_id, self.vacancy_desirability(&city.units[u_id], city, tenants, conf)))
+                .filter(|&(_, desirability)| desirability > min_desirability)
+                .fold(best, |acc, (u_id, desirability)| match acc {
+                    Some((_, best)) if best >= desirability => acc,
+                    _ => Some((u_id, desirability))
+                });
+
+            if let Some((best_id, _)) = best {
+                if let Some(u_id) = self.unit {
+                    for &t_id in &self.members {
+                        events.emit(EventKind::MovedOut { tenant: t_id, unit: u_id });
+                    }
+                    self.move_out(tenants, city);
+                    vacated.push(u_id);
+                }
+
+                // A new lease
+                let unit = &mut city.units[best_id];
+                unit.lease_month = month % 12;
+                unit.months_vacant = 0;
+                self.move_in(best_id, tenants, city);
+                for &t_id in &self.members {
+                    events.emit(EventKind::MovedIn { tenant: t_id, unit: best_id });
+                }
+            }
+        }
+
+        if let Some((u_id, amount)) = decision.offer {
+            if let Some(buyer) = self.head(tenants) {
+                city.units[u_id].offers.push((AgentType::Tenant, buyer, amount));
+                events.emit(EventKind::OfferMade { unit: u_id, buyer: (AgentType::Tenant, buyer), amount });
+            }
+        }
+    }
+
+    // Pay the month's rent and any arrears, first out of the share
+    // of members' incomes that goes to housing, then out of their
+    // savings. Whatever's left of that share is saved. Owner-occupiers
+    // pay their mortgage and upkeep instead, and fall behind on those.
+    // Returns what each member paid.
+    pub fn pay_rent(&self, tenants: &mut [Tenant], city: &mut City, conf: &Config) -> Vec<(usize, f32)> {
+        let owner = self.owns_home(city);
+        let due = match self.unit {
+            Some(u_id) => {
+                let unit = &city.units[u_id];
+                let cost = if owner {
+                    self.ownership_costs(unit, tenants, conf)
+                } else {
+                    unit.rent
+                };
+                cost + unit.arrears
+            },
+            None => 0.
+        };
+        let budget = self.income(tenants) * conf.rent_payment_capacity;
+        // Members in debt, e.g. after selling
+        // a home under water, have nothing to draw on
+        let savings: f32 = self.members.iter().map(|&t_id| f32::max(tenants[t_id].savings, 0.)).sum();
+        let from_income = f32::min(due, budget);
+        let from_savings = f32::min(due - from_income, savings);
+
+        // Members chip in in proportion
+        // to their incomes and savings
+        let payments: Vec<(usize, f32)> = self.members.iter().map(|&t_id| {
+            let tenant = &mut tenants[t_id];
+            let tenant_budget = tenant.income * conf.rent_payment_capacity;
+            let mut paid = 0.;
+            if budget > 0. {
+                paid += from_income * tenant_budget / budget;
+            }
+            if savings > 0. {
+                paid += from_savings * f32::max(tenant.savings, 0.) / savings;
+            }
+            tenant.savings += tenant_budget - paid;
+            tenant.last_rent = paid;
+            (t_id, paid)
+        }).collect();
+
+        if let Some(u_id) = self.unit {
+            let unit = &mut city.units[u_id];
+            let paid = from_income + from_savings;
+            unit.arrears = due - paid;
+            if owner {
+                for &t_id in &self.members {
+                    let tenant = &mut tenants[t_id];
+                    for mortgage in tenant.mortgages.iter_mut().filter(|m| m.unit == u_id) {
+                        mortgage.pay();
+                    }
+                    tenant.mortgages.retain(|m| !m.paid_off());
+                }
+            } else {
+                unit.rent_paid = paid;
+            }
+        }
+        payments
+    }
+
+    pub fn move_in(&mut self, unit_id: usize, tenants: &mut [Tenant], city: &mut City) {
+        let unit = &mut city.units[unit_id];
+
+        // Nothing's owed on a new lease
+        if unit.vacant() {
+            unit.arrears = 0.;
+            unit.eviction = None;
+        }
+        for &t_id in &self.members {
+            unit.tenants.insert(t_id);
+            tenants[t_id].unit = Some(unit_id);
+        }
+        self.unit = Some(unit_id);
+    }
+
+    pub fn move_out(&mut self, tenants: &mut [Tenant], city: &mut City) {
+        if let Some(u_id) = self.unit {
+            let unit = &mut city.units[u_id];
+            for &t_id in &self.members {
+                unit.tenants.remove(&t_id);
+                tenants[t_id].unit = None;
+            }
+        }
+        self.unit = None;
+    }
+
+    // Remove a member, who moves out
+    // if the household has a home
+    fn remove(&mut self, t_id: usize, tenants: &mut [Tenant], city: &mut City, events: &mut EventLog) {
+        if let Some(u_id) = self.unit {
+            events.emit(EventKind::MovedOut { tenant: t_id, unit: u_id });
+        }
+        self.remove_member(t_id, tenants, city);
+        events.emit(EventKind::LeftHousehold { tenant: t_id, household: self.id });
+    }
+
+    // Add a tenant who has left their old household
+    fn add(&mut self, t_id: usize, tenants: &mut [Tenant], city: &mut City, events: &mut EventLog) {
+        self.add_member(t_id, tenants, city);
+        if let Some(u_id) = self.unit {
+            events.emit(EventKind::MovedIn { tenant: t_id, unit: u_id });
+        }
+        events.emit(EventKind::JoinedHousehold { tenant: t_id, household: self.id });
+    }
+
+    pub fn remove_member(&mut self, t_id: usize, tenants: &mut [Tenant], city: &mut City) {
+        self.members.retain(|&m| m != t_id);
+        if let Some(u_id) = self.unit {
+            city.units[u_id].tenants.remove(&t_id);
+            tenants[t_id].unit = None;
+        }
+        if self.dissolved() {
+            self.unit = None;
+        } else if self.size() == 1 {
+            self.kind = HouseholdKind::Single;
+        }
+    }
+
+    pub fn add_member(&mut self, t_id: usize, tenants: &mut [Tenant], city: &mut City) {
+        self.members.push(t_id);
+        tenants[t_id].household = self.id;
+        if let Some(u_id) = self.unit {
+            city.units[u_id].tenants.insert(t_id);
+            tenants[t_id].unit = Some(u_id);
+        }
+    }
+
+    pub fn adults(&self, tenants: &[Tenant]) -> Vec<usize> {
+        self.members.iter()
+            .cloned()
+            .filter(|&t_id| tenants[t_id].employment != Employment::Child)
+            .collect()
+    }
+}
+
+// Households shaped to fit the city's units, enough
+// to fill `POP_P_OCCUPANCY` of them. Members are numbered
+// in order, so these are the ids of the tenants to create.
+pub fn form_households(city: &City, rng: &mut StdRng, conf: &Config) -> Vec<Household> {
+    let kinds = [HouseholdKind::Single, HouseholdKind::Family, HouseholdKind::Roommates];
+    let kind_weights = [conf.household_p_single, conf.household_p_family, conf.household_p_roommates];
+    let kind_dist = WeightedIndex::new(kind_weights).expect("invalid household shares");
+
+    let mut units: Vec<&Unit> = city.units.iter().collect();
+    units.shuffle(rng);
+
+    let n_households = (conf.pop_p_occupancy * units.len() as f32).round() as usize;
+    let mut households = Vec::new();
+    let mut next_tenant = 0;
+    for unit in units.iter().take(n_households) {
+        let kind = kinds[kind_dist.sample(rng)];
+        let size = match kind {
+            HouseholdKind::Single => 1,
+
+            // Couples, with children
+            // in the other bedrooms
+            HouseholdKind::Family => rng.gen_range(2, unit.occupancy + 2),
+
+            // Roommates need at least two bedrooms
+            HouseholdKind::Roommates => max(unit.occupancy, 2),
+        };
+        let members = (next_tenant..next_tenant + size).collect();
+        households.push(Household::new(households.len(), kind, members));
+        next_tenant += size;
+    }
+    households
+}
+
+// Households form and break up: singles pair up as couples,
+// members of larger households move out on their own,
+// and homeless singles team up as roommates.
+// Households with players are left alone.
+pub fn update_households(
+    households: &mut Vec<Household>,
+    tenants: &mut [Tenant],
+    city: &mut City,
+    rng: &mut StdRng,
+    conf: &Config,
+    events: &mut EventLog,
+) {
+    // Members moving out on their own
+    let mut leaving = Vec::new();
+    for household in households.iter() {
+        if household.size() < 2 || household.player(tenants) {
+            continue;
+        }
+        let p_leave = match household.kind {
+            HouseholdKind::Family => conf.family_split_rate,
+            _ => conf.roommate_leave_rate,
+        };
+        // Children don't leave until they're adults
+        let roll: f32 = rng.gen();
+        if roll < p_leave {
+            if let Some(&t_id) = household.adults(tenants).choose(rng) {
+                leaving.push(t_id);
+            }
+        }
+    }
+    for t_id in leaving {
+        leave_household(households, t_id, tenants, city, events);
+    }
+
+    // Singles pairing up
+    let singles: Vec<usize> = households.iter()
+        .filter(|h| h.kind == HouseholdKind::Single && !h.dissolved() && !h.player(tenants))
+        .map(|h| h.id)
+        .collect();
+    let mut paired = vec![false; households.len()];
+    for &h_id in &singles {
+        let roll: f32 = rng.gen();
+        if paired[h_id] || roll >= conf.partner_rate {
+            continue;
+        }
+        let &partner = singles.choose(rng).unwrap();
+        if partner == h_id || paired[partner] {
+            continue;
+        }
+        paired[h_id] = true;
+        paired[partner] = true;
+
+        // The couple keeps the bigger of their homes
+        let bedrooms = |h: &Household| h.unit.map_or(0, |u_id| city.units[u_id].occupancy);
+        let (host, joiner) = if bedrooms(&households[partner]) > bedrooms(&households[h_id]) {
+            (partner, h_id)
+        } else {
+            (h_id, partner)
+        };
+        merge(households, host, joiner, HouseholdKind::Family, tenants, city, events);
+    }
+
+    // Homeless singles teaming up
+    let mut homeless: Vec<usize> = households.iter()
+        .filter(|h| h.kind == HouseholdKind::Single && h.unit.is_none() && !h.dissolved() && !h.player(tenants))
+        .map(|h| h.id)
+        .collect();
+    homeless.shuffle(rng);
+    for pair in homeless.chunks(2) {
+        let roll: f32 = rng.gen();
+        if pair.len() == 2 && roll < conf.roommate_match_rate {
+            merge(households, pair[0], pair[1], HouseholdKind::Roommates, tenants, city, events);
+        }
+    }
+}
+
+// A tenant strikes out on their own as a new,
+// homeless single household; returns its id
+pub fn leave_household(
+    households: &mut Vec<Household>,
+    t_id: usize,
+    tenants: &mut [Tenant],
+    city: &mut City,
+    events: &mut EventLog,
+) -> usize {
+    let h_id = tenants[t_id].household;
+    households[h_id].remove(t_id, tenants, city, events);
+    let id = households.len();
+    tenants[t_id].household = id;
+    households.push(Household::new(id, HouseholdKind::Single, vec![t_id]));
+    id
+}
+
+// Move the joining household's members into the host household
+fn merge(
+    households: &mut [Household],
+    host: usize,
+    joiner: usize,
+    kind: HouseholdKind,
+    tenants: &mut [Tenant],
+    city: &mut City,
+    events: &mut EventLog,
+) {
+    let members = households[joiner].members.clone();
+    for t_id in members {
+        households[joiner].remove(t_id, tenants, city, events);
+        households[host].add(t_id, tenants, city, events);
+    }
+    households[host].kind = kind;
+}
@@ -0,0 +1,61 @@
+//! Simulation of a city's housing market with DOMA,
+//! a tenant-owned real estate fund.
+//!
+//! ```no_run
+//! use doma_sim::{config, design, snapshot, stats, Simulation};
+//! use std::path::Path;
+//!
+//! let mut conf = config::load_config(Path::new("config.yaml"), &[]).unwrap();
+//! conf.seed = 42;
+//! let source = design::DesignSource::parse(&conf.design_source);
+//! let design = design::load_design(&source, &conf.design_id).unwrap();
+//!
+//! // All of the simulation's randomness derives from `conf.seed`
+//! let mut sim = Simulation::new(design, conf).unwrap();
+//! for _ in 0..12 {
+//!     sim.step();
+//! }
+//! println!("{}", stats::stats(&sim));
+//!
+//! // Save and pick up later
+//! snapshot::save_snapshot(&sim, Path::new("year1.snap")).unwrap();
+//! let mut sim = snapshot::load_snapshot(Path::new("year1.snap")).unwrap();
+//! sim.step();
+//! ```
+
+// For the large `json!` in stats
+#![recursion_limit = "256"]
+
+pub mod acquisition;
+pub mod agent;
+pub mod city;
+pub mod config;
+pub mod design;
+pub mod economy;
+pub mod events;
+pub mod eviction;
+pub mod finance;
+pub mod generate;
+pub mod governance;
+pub mod grid;
+pub mod household;
+pub mod policy;
+pub mod population;
+pub mod rent;
+pub mod run;
+pub mod shares;
+pub mod sim;
+pub mod snapshot;
+pub mod social;
+pub mod stats;
+pub mod sweep;
+
+#[cfg(feature = "server")]
+pub mod play;
+#[cfg(feature = "server")]
+pub mod sync;
+
+pub use self::config::Config;
+pub use self::design::Design;
+pub use self::policy::Policy;
+pub use self::sim::Simulation;
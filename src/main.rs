@@ -1,145 +1,643 @@
-extern crate chrono;
-extern crate md5;
-extern crate noise;
-extern crate pbr;
-extern crate rand;
-extern crate redis;
-extern crate serde;
-extern crate sentry;
-extern crate serde_json;
-extern crate serde_yaml;
-extern crate petgraph;
-extern crate rand_distr;
-
-mod agent;
-mod social;
-mod city;
-mod config;
-mod design;
-mod grid;
-mod play;
-mod sim;
-mod stats;
-mod sync;
-mod policy;
-use self::config::Config;
-use self::sim::Simulation;
-use self::play::{PlayManager, Control};
+use doma_sim::{config, design, events, generate, run, snapshot, sweep};
+use doma_sim::agent::AgentType;
+use doma_sim::events::{Agent, EventQuery, EventWriter};
+use doma_sim::run::RunOutput;
+use doma_sim::config::Config;
+use doma_sim::design::{Design, DesignSource};
+#[cfg(feature = "server")]
+use doma_sim::{stats, sync};
+use doma_sim::sim::Simulation;
+#[cfg(feature = "server")]
+use doma_sim::play::{PlayManager, Control};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 use pbr::ProgressBar;
-use rand::rngs::StdRng;
-use rand::SeedableRng;
-use serde_json::{json, Value};
+use rand::Rng;
+#[cfg(feature = "server")]
+use rand::{rngs::StdRng, SeedableRng};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
+use std::process;
 use std::os::unix::fs::symlink;
-use std::path::Path;
-use chrono::{DateTime, Utc, Local};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+#[cfg(feature = "server")]
+use chrono::Local;
+#[cfg(feature = "sentry")]
 use sentry::integrations::panic::register_panic_handler;
 
-fn save_run_data(sim: &Simulation, history: &Vec<Value>, init: &Value, conf: &Config) {
+// Write to a timestamped directory in the output dir,
+// and point `latest` at it
+fn save_run_data(output: &RunOutput, dir: &Path) {
     let now: DateTime<Utc> = Utc::now();
     let now_str = now.format("%Y.%m.%d.%H.%M.%S").to_string();
-    let results = json!({
-        "init": init,
-        "history": history,
-        "meta": {
-            "seed": conf.seed,
-            "design": conf.design_id,
-            "tenants": sim.tenants.len(),
-            "units": sim.city.units.len(),
-            "occupancy": sim.city.units.iter().fold(0, |acc, u| acc + u.occupancy),
-            "neighborhoods": sim.design.neighborhoods,
-        }
-    })
-    .to_string();
-
-    let dir = format!("runs/{}", now_str);
-    let fname = format!("runs/{}/output.json", now_str);
 
-    let path = Path::new(&dir);
+    let path = dir.join(&now_str);
     let run_path = Path::new(&now_str);
-    let latest_path = Path::new("runs/latest");
-    fs::create_dir(path).unwrap();
-    fs::write(fname, results).expect("Unable to write file");
-    if latest_path.exists() {
-        fs::remove_file(latest_path).unwrap();
+    let latest_path = dir.join("latest");
+    run::write_run_data(output, &path);
+    if fs::symlink_metadata(&latest_path).is_ok() {
+        fs::remove_file(&latest_path).unwrap();
     }
     symlink(run_path, latest_path).unwrap();
-
-    let conf_path = Path::join(path, Path::new("config.yaml"));
-    fs::copy(Path::new("config.yaml"), conf_path).unwrap();
     println!("Wrote output to {:?}", path);
 }
 
-fn main() {
-    let conf = config::load_config();
-    let debug = conf.debug;
-    let steps = conf.steps;
-    let mut rng: StdRng = SeedableRng::seed_from_u64(conf.seed);
+// Print any problems with the design,
+// returning whether it's safe to simulate
+fn check_design(design: &Design) -> bool {
+    let errors = design.validate();
+    for err in &errors {
+        let level = if err.is_fatal() { "error" } else { "warning" };
+        println!("{}: {}", level, err);
+    }
+    !errors.iter().any(|err| err.is_fatal())
+}
+
+fn load_checked_design(source: &DesignSource, conf: &Config) -> Design {
+    let design = load_design(source, conf);
+    if !check_design(&design) {
+        process::exit(1);
+    }
+    design
+}
+
+fn load_design(source: &DesignSource, conf: &Config) -> Design {
+    match design::load_design(source, &conf.design_id) {
+        Ok(design) => design,
+        Err(err) => {
+            println!("error: {}", err);
+            process::exit(1);
+        }
+    }
+}
 
-    let _guard = sentry::init(conf.sentry_dsn.clone());
-    register_panic_handler();
+// Report errors with bad input and exit,
+// rather than panicking with a backtrace
+fn or_exit<T, E: fmt::Display>(result: Result<T, E>) -> T {
+    match result {
+        Ok(val) => val,
+        Err(err) => {
+            println!("error: {}", err);
+            process::exit(1);
+        }
+    }
+}
+
+fn new_simulation(design: Design, conf: Config) -> Simulation {
+    match Simulation::new(design, conf) {
+        Ok(sim) => sim,
+        Err(err) => {
+            println!("error: {}", err);
+            process::exit(1);
+        }
+    }
+}
+
+// Run headless with a progress bar,
+// optionally writing events as they happen
+fn run_headless(sim: Simulation, steps: usize, events_path: Option<&str>) -> RunOutput {
+    let mut pb = ProgressBar::new(steps as u64);
+    let mut events = events_path.map(|path| EventWriter::create(Path::new(path)));
+    println!("{:?} tenants", sim.tenants.len());
+    let output = run::run_from(sim, steps, |sim| {
+        if let Some(writer) = &mut events {
+            writer.write(&sim.events.events);
+        }
+        pb.inc();
+    });
+    if let Some(writer) = &mut events {
+        writer.flush();
+    }
+    output
+}
+
+// Argument validators, so bad values are
+// reported by clap along with the usage
+fn is_integer(val: String) -> Result<(), String> {
+    match val.parse::<u64>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(format!("must be a non-negative integer, got {:?}", val))
+    }
+}
 
+fn is_agent(val: String) -> Result<(), String> {
+    parse_agent(&val).map(|_| ())
+}
+
+// Agents are given as `Type:id`, e.g. `Landlord:3`.
+// The id can be left out for the citywide DOMA.
+fn parse_agent(agent: &str) -> Result<Agent, String> {
+    let parts: Vec<&str> = agent.splitn(2, ":").collect();
+    let typ = match parts[0] {
+        "Tenant" => AgentType::Tenant,
+        "Landlord" => AgentType::Landlord,
+        "DOMA" => AgentType::DOMA,
+        _ => return Err(format!("agents must be of the form Type:id, e.g. Landlord:3, got {:?}", agent))
+    };
+    let id = match parts.get(1) {
+        Some(id) => id.parse().map_err(|_| format!("agent id must be an integer, got {:?}", id))?,
+        None => 0
+    };
+    Ok((typ, id))
+}
+
+// Query an event log
+fn query_events(args: &ArgMatches) {
+    // Validated by clap
+    let parse = |key: &str| args.value_of(key).map(|v| v.parse().unwrap());
+    let query = EventQuery {
+        types: match args.values_of("type") {
+            Some(vals) => vals.map(|v| v.to_string()).collect(),
+            None => Vec::new()
+        },
+        tenant: parse("tenant"),
+        unit: parse("unit"),
+        agent: args.value_of("agent").map(|agent| parse_agent(agent).unwrap()),
+        from: parse("from"),
+        to: parse("to"),
+    };
+    let matched = events::load_events(Path::new(args.value_of("EVENTS").unwrap()))
+        .into_iter()
+        .filter(|event| query.matches(event));
+    if args.is_present("count") {
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for event in matched {
+            *counts.entry(event.kind.name()).or_insert(0) += 1;
+        }
+        for (name, count) in counts {
+            println!("{},{}", name, count);
+        }
+    } else {
+        for event in matched {
+            println!("{}", serde_json::to_string(&event).unwrap());
+        }
+    }
+}
+
+fn load_snapshot_or_exit(path: &Path) -> Simulation {
+    match snapshot::load_snapshot(path) {
+        Ok(sim) => sim,
+        Err(err) => {
+            println!("error: could not load snapshot {:?}: {}", path, err);
+            process::exit(1);
+        }
+    }
+}
+
+// Missing or outdated snapshots aren't fatal
+// when they're only used as a cache
+#[cfg(feature = "server")]
+fn try_load_snapshot(path: &Path) -> Option<Simulation> {
+    if !path.exists() {
+        return None;
+    }
+    match snapshot::load_snapshot(path) {
+        Ok(sim) => {
+            println!("Loaded snapshot {:?}", path);
+            Some(sim)
+        },
+        Err(err) => {
+            println!("Ignoring snapshot {:?}: {}", path, err);
+            None
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+fn save_snapshot(sim: &Simulation, path: &Path) {
+    match snapshot::save_snapshot(sim, path) {
+        Ok(()) => println!("Saved snapshot {:?}", path),
+        Err(err) => println!("error: could not save snapshot {:?}: {}", path, err)
+    }
+}
+
+// Where the game server keeps its snapshots
+#[cfg(feature = "server")]
+struct ServeSnapshots {
+    // A burned-in world to start every session from
+    burn_in: Option<PathBuf>,
+
+    // The current session, saved after every run
+    session: Option<PathBuf>,
+}
+
+// Create a new world and burn it in,
+// or load a previously burned-in one
+#[cfg(feature = "server")]
+fn new_session(conf: Config, design_source: &DesignSource, snapshots: &ServeSnapshots) -> Simulation {
+    if let Some(path) = &snapshots.burn_in {
+        if let Some(sim) = try_load_snapshot(path) {
+            return sim;
+        }
+    }
+
+    let design = load_checked_design(design_source, &conf);
+    let mut sim = new_simulation(design, conf);
+    println!("{:?} tenants", sim.tenants.len());
+    println!("Burning in for {:?} months...", sim.conf.burn_in);
+    for _ in 0..sim.conf.burn_in {
+        sim.step();
+    }
+    if let Some(path) = &snapshots.burn_in {
+        save_snapshot(&sim, path);
+    }
+    sim
+}
+
+// Run the interactive game loop,
+// controlled by commands sent over redis
+#[cfg(feature = "server")]
+fn serve(conf: Config, design_source: &DesignSource, snapshots: &ServeSnapshots) {
+    // Each session gets its own seed
+    let mut rng: StdRng = SeedableRng::seed_from_u64(conf.seed);
     let mut play = PlayManager::new();
     loop {
         play.set_loading().unwrap();
-
-        // Load and setup world
-        let design = design::load_design(&conf.design_id);
-        let mut sim = Simulation::new(design, conf.clone(), &mut rng);
-        println!("{:?} tenants", sim.tenants.len());
         play.reset().unwrap();
 
-        if debug {
-            let init_stats = stats::init_stats(&sim);
-            let mut history = Vec::with_capacity(steps);
-            let mut pb = ProgressBar::new(steps as u64);
-            for _ in 0..steps {
-                sim.step(&mut rng);
-                history.push(stats::stats(&sim));
-                pb.inc();
+        // Resume the saved session, if any,
+        // otherwise load and setup a new world
+        let resumed = match &snapshots.session {
+            Some(path) => try_load_snapshot(path),
+            None => None
+        };
+        let mut sim = match resumed {
+            Some(mut sim) => {
+                // Players have to rejoin
+                for t in &mut sim.tenants {
+                    t.player = false;
+                }
+                sim
+            },
+            None => {
+                let mut conf = conf.clone();
+                conf.seed = rng.gen();
+                println!("Session seed: {}", conf.seed);
+                new_session(conf, design_source, snapshots)
             }
-            save_run_data(&sim, &history, &init_stats, &sim.conf);
-
-            // Run only once
-            break;
+        };
 
-        } else {
+        // Setup tenants for players to choose
+        let mut pool_rng = sim.player_rng();
+        play.gen_player_tenant_pool(&sim.tenants, &sim.city, sim.conf.tenant_pool_size, &mut pool_rng);
+        sync::sync(sim.time, &sim.city, &sim.design, stats::stats(&sim)).unwrap();
+        play.set_ready().unwrap();
+        println!("Ready: Session {}", Local::now().to_rfc3339());
 
-            // Setup tenants for players to choose
-            play.gen_player_tenant_pool(&sim.tenants, &sim.city, sim.conf.tenant_pool_size);
-            println!("Burning in for {:?} months...", sim.conf.burn_in);
-            for _ in 0..sim.conf.burn_in {
-                sim.step(&mut rng);
-            }
-            sync::sync(sim.time, &sim.city, &sim.design, stats::stats(&sim)).unwrap();
-            play.set_ready().unwrap();
-            println!("Ready: Session {}", Local::now().to_rfc3339());
-
-            loop {
-                // Blocks until a run command is received;
-                // will process other commands while waiting
-                let control = play.wait_for_control(&mut sim, &mut rng);
-                match control {
-                    Control::Run(steps) => {
-                        println!("Running for {:?} steps...", steps);
-                        let mut pb = ProgressBar::new(steps as u64);
-                        play.set_running().unwrap();
-                        for step in 0..steps {
-                            sim.step(&mut rng);
-                            play.sync_step(step, steps).unwrap();
-                            pb.inc();
+        loop {
+            // Blocks until a run command is received;
+            // will process other commands while waiting
+            let control = play.wait_for_control(&mut sim);
+            match control {
+                Control::Run(steps) => {
+                    println!("Running for {:?} steps...", steps);
+                    let mut pb = ProgressBar::new(steps as u64);
+                    play.set_running().unwrap();
+                    for step in 0..steps {
+                        sim.step();
+                        play.sync_step(step, steps).unwrap();
+                        pb.inc();
+                    }
+                    sync::sync(sim.time, &sim.city, &sim.design, stats::stats(&sim)).unwrap();
+                    play.sync_players(&sim.tenants, &sim.households, &sim.city, &sim.domas).unwrap();
+                    if let Some(path) = &snapshots.session {
+                        save_snapshot(&sim, path);
+                    }
+                    play.set_ready().unwrap();
+                    println!("Finished running.");
+                },
+                Control::Reset => {
+                    println!("Resetting...");
+                    if let Some(path) = &snapshots.session {
+                        if path.exists() {
+                            fs::remove_file(path).unwrap();
                         }
-                        sync::sync(sim.time, &sim.city, &sim.design, stats::stats(&sim)).unwrap();
-                        play.sync_players(&sim.tenants, &sim.city, &sim.doma).unwrap();
-                        play.set_ready().unwrap();
-                        println!("Finished running.");
-                    },
-                    Control::Reset => {
-                        println!("Resetting...");
-                        break;
                     }
+                    break;
                 }
             }
         }
     }
 }
+
+// Load the config, applying the
+// overrides and flags shared by all subcommands
+fn load_config(config_path: &Path, args: &ArgMatches, extra: &[(String, String)]) -> Config {
+    let mut overrides = overrides(args);
+    overrides.extend(extra.iter().cloned());
+    let mut conf = match config::load_config(config_path, &overrides) {
+        Ok(conf) => conf,
+        Err(err) => {
+            println!("error: {}", err);
+            process::exit(1);
+        }
+    };
+    if let Some(design_id) = args.value_of("design") {
+        conf.design_id = design_id.to_string();
+    }
+    if let Some(source) = args.value_of("design-source") {
+        conf.design_source = source.to_string();
+    }
+    if let Some(steps) = args.value_of("steps") {
+        conf.steps = steps.parse().unwrap();
+    }
+    conf
+}
+
+fn overrides(args: &ArgMatches) -> Vec<(String, String)> {
+    let vals = match args.values_of("set") {
+        Some(vals) => vals,
+        None => return Vec::new()
+    };
+    match config::parse_overrides(vals) {
+        Ok(overrides) => overrides,
+        Err(err) => {
+            println!("error: {}", err);
+            process::exit(1);
+        }
+    }
+}
+
+// Explicit seeds, from the flag or `--set SEED=...`, take precedence,
+// otherwise generate one (it's recorded with the run output)
+fn seed(args: &ArgMatches) -> u64 {
+    let set_seed = overrides(args).into_iter().rev()
+        .find(|(key, _)| key.to_uppercase() == "SEED")
+        .map(|(_, val)| val);
+    match args.value_of("seed").map(|s| s.to_string()).or(set_seed) {
+        Some(seed) => match seed.parse() {
+            Ok(seed) => seed,
+            Err(_) => {
+                println!("error: seed must be an integer, got {:?}", seed);
+                process::exit(1);
+            }
+        },
+        None => {
+            let seed = rand::thread_rng().gen();
+            println!("No seed given, using {}", seed);
+            seed
+        }
+    }
+}
+
+fn main() {
+    let config_arg = Arg::with_name("config")
+        .short("c").long("config").takes_value(true).global(true)
+        .default_value("config.yaml")
+        .help("Path to the config file");
+    let set_arg = Arg::with_name("set")
+        .long("set").takes_value(true).multiple(true).number_of_values(1).global(true)
+        .help("Override a config value, e.g. --set DOMA_P_RESERVES=0.3");
+    let design_arg = Arg::with_name("design")
+        .long("design").takes_value(true).global(true)
+        .help("Design id, overrides DESIGN_ID");
+    let design_source_arg = Arg::with_name("design-source")
+        .short("d").long("design-source").takes_value(true).global(true)
+        .help("Design source (redis url, file, directory or \"generate\"), overrides DESIGN_SOURCE");
+    let seed_arg = Arg::with_name("seed")
+        .short("s").long("seed").takes_value(true).validator(is_integer)
+        .help("Random seed; generated if not given");
+    let steps_arg = Arg::with_name("steps")
+        .short("n").long("steps").takes_value(true).validator(is_integer)
+        .help("Number of steps (months) to run");
+    let output_arg = Arg::with_name("output")
+        .short("o").long("output").takes_value(true).default_value("runs")
+        .help("Directory to write run output to");
+    let events_arg = Arg::with_name("events")
+        .long("events").takes_value(true)
+        .help("Write every event to this file, as JSON Lines (.jsonl) or CSV (.csv)");
+
+    let args = App::new("doma_sim")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(config_arg)
+        .arg(set_arg)
+        .arg(design_arg)
+        .arg(design_source_arg)
+        .subcommand(SubCommand::with_name("run")
+            .about("Run a headless simulation and save its stats")
+            .arg(seed_arg.clone())
+            .arg(steps_arg.clone().default_value("100"))
+            .arg(output_arg.clone())
+            .arg(events_arg.clone())
+            .arg(Arg::with_name("resume").long("resume").takes_value(true)
+                 .help("Continue from a snapshot instead of creating a new simulation"))
+            .arg(Arg::with_name("save-snapshot").long("save-snapshot").takes_value(true)
+                 .help("Save the final simulation state to a snapshot")))
+        .subcommand(SubCommand::with_name("serve")
+            .about("Run the interactive game loop over redis")
+            .arg(seed_arg.clone())
+            .arg(Arg::with_name("burn-in-snapshot").long("burn-in-snapshot").takes_value(true)
+                 .help("Snapshot of a burned-in world to start sessions from; created if missing"))
+            .arg(Arg::with_name("session-snapshot").long("session-snapshot").takes_value(true)
+                 .help("Save the session here after every run, and resume from it on restart")))
+        .subcommand(SubCommand::with_name("validate-design")
+            .about("Check a design for problems"))
+        .subcommand(SubCommand::with_name("generate")
+            .about("Generate a city design")
+            .arg(Arg::with_name("SEED").required(true).validator(is_integer).help("Generator seed"))
+            .arg(Arg::with_name("OUTPUT").required(true).help("Output path (.json or .yaml)"))
+            .arg(Arg::with_name("params").short("p").long("params").takes_value(true)
+                 .help("Generator parameters (YAML)")))
+        .subcommand(SubCommand::with_name("sweep")
+            .about("Run headless simulations over combinations of config values, in parallel")
+            .arg(Arg::with_name("SPEC").help("Sweep spec (YAML) with GRID, RUNS, SEEDS and STEPS"))
+            .arg(Arg::with_name("vary").long("vary").takes_value(true).multiple(true).number_of_values(1)
+                 .help("Config values to sweep, e.g. --vary DOMA_P_RENT_SHARE=0.1,0.2"))
+            .arg(Arg::with_name("seeds").long("seeds").takes_value(true).validator(is_integer)
+                 .help("Number of seeds to run per combination [default: 1]"))
+            .arg(Arg::with_name("jobs").short("j").long("jobs").takes_value(true).default_value("0").validator(is_integer)
+                 .help("Number of runs in parallel, 0 for one per core"))
+            .arg(seed_arg.clone().help("Base seed; run seeds are derived from it"))
+            .arg(steps_arg.clone().default_value("100"))
+            .arg(output_arg.clone().default_value("sweeps")))
+        .subcommand(SubCommand::with_name("verify-determinism")
+            .about("Run the same config twice, on different numbers of threads, and check every step's state matches")
+            .arg(seed_arg.clone())
+            .arg(steps_arg.clone().default_value("24")))
+        .subcommand(SubCommand::with_name("replay")
+            .about("Re-run a saved run with its recorded config and seed")
+            .arg(Arg::with_name("RUN").required(true).help("Run output directory"))
+            .arg(steps_arg.clone())
+            .arg(output_arg.clone())
+            .arg(events_arg.clone()))
+        .subcommand(SubCommand::with_name("events")
+            .about("Query an event log (JSON Lines)")
+            .arg(Arg::with_name("EVENTS").required(true).help("Event log written with --events"))
+            .arg(Arg::with_name("type").short("t").long("type").takes_value(true).multiple(true).number_of_values(1)
+                 .help("Event type, e.g. Sale or MovedIn"))
+            .arg(Arg::with_name("tenant").long("tenant").takes_value(true).validator(is_integer).help("Tenant id"))
+            .arg(Arg::with_name("unit").long("unit").takes_value(true).validator(is_integer).help("Unit id"))
+            .arg(Arg::with_name("agent").long("agent").takes_value(true).validator(is_agent)
+                 .help("Buyer or seller, e.g. Landlord:3 or DOMA"))
+            .arg(Arg::with_name("from").long("from").takes_value(true).validator(is_integer).help("First month"))
+            .arg(Arg::with_name("to").long("to").takes_value(true).validator(is_integer).help("Last month"))
+            .arg(Arg::with_name("count").long("count").help("Print counts by event type instead")))
+        .get_matches();
+
+    let config_path = PathBuf::from(args.value_of("config").unwrap());
+    match args.subcommand() {
+        ("run", Some(sub)) => {
+            let mut conf = load_config(&config_path, sub, &[]);
+            conf.debug = true;
+            conf.seed = seed(sub);
+            println!("{:?}", conf);
+
+            let steps = conf.steps;
+            let sim = match sub.value_of("resume") {
+                // The snapshot has its own config
+                Some(path) => load_snapshot_or_exit(Path::new(path)),
+                None => {
+                    let design_source = DesignSource::parse(&conf.design_source);
+                    let design = load_checked_design(&design_source, &conf);
+                    new_simulation(design, conf)
+                }
+            };
+            let output = run_headless(sim, steps, sub.value_of("events"));
+            save_run_data(&output, Path::new(sub.value_of("output").unwrap()));
+            if let Some(path) = sub.value_of("save-snapshot") {
+                if let Err(err) = snapshot::save_snapshot(&output.sim, Path::new(path)) {
+                    println!("error: could not save snapshot {:?}: {}", path, err);
+                    process::exit(1);
+                }
+                println!("Saved snapshot {:?}", path);
+            }
+        },
+        #[cfg(feature = "server")]
+        ("serve", Some(sub)) => {
+            let mut conf = load_config(&config_path, sub, &[]);
+            conf.debug = false;
+            conf.seed = seed(sub);
+            println!("{:?}", conf);
+
+            #[cfg(feature = "sentry")]
+            let _guard = sentry::init(conf.sentry_dsn.clone());
+            #[cfg(feature = "sentry")]
+            register_panic_handler();
+
+            let design_source = DesignSource::parse(&conf.design_source);
+            let snapshots = ServeSnapshots {
+                burn_in: sub.value_of("burn-in-snapshot").map(PathBuf::from),
+                session: sub.value_of("session-snapshot").map(PathBuf::from),
+            };
+            serve(conf, &design_source, &snapshots);
+        },
+        #[cfg(not(feature = "server"))]
+        ("serve", Some(_)) => {
+            println!("doma_sim was built without the `server` feature");
+            process::exit(1);
+        },
+        ("validate-design", Some(sub)) => {
+            let conf = load_config(&config_path, sub, &[]);
+            let design_source = DesignSource::parse(&conf.design_source);
+            let design = load_design(&design_source, &conf);
+            if check_design(&design) {
+                println!("Design {:?} is valid", conf.design_id);
+            } else {
+                process::exit(1);
+            }
+        },
+        ("generate", Some(sub)) => {
+            let seed: u64 = sub.value_of("SEED").unwrap().parse().unwrap();
+            let params = match sub.value_of("params") {
+                Some(path) => match generate::load_generator_config(Path::new(path)) {
+                    Ok(params) => params,
+                    Err(err) => {
+                        println!("error: {}", err);
+                        process::exit(1);
+                    }
+                },
+                None => generate::GeneratorConfig::default()
+            };
+            let design = generate::generate(seed, &params);
+            if !check_design(&design) {
+                process::exit(1);
+            }
+            let output = sub.value_of("OUTPUT").unwrap();
+            design::save_design(&design, Path::new(output));
+            println!("Wrote design to {:?}", output);
+        },
+        ("sweep", Some(sub)) => {
+            let mut spec = match sub.value_of("SPEC") {
+                Some(path) => or_exit(sweep::load_spec(Path::new(path))),
+                None => sweep::SweepSpec::default()
+            };
+            if let Some(vals) = sub.values_of("vary") {
+                for (key, vals) in or_exit(sweep::parse_vary(vals)) {
+                    spec.vary(key, vals);
+                }
+            }
+            if let Some(seeds) = sub.value_of("seeds") {
+                spec.seeds = Some(seeds.parse().unwrap());
+            }
+
+            // Check the base design once up front
+            // rather than in every run
+            let conf = load_config(&config_path, sub, &[]);
+            let design_source = DesignSource::parse(&conf.design_source);
+            load_checked_design(&design_source, &conf);
+
+            let opts = sweep::SweepOptions {
+                config_path: config_path.clone(),
+                overrides: overrides(sub),
+                design_id: sub.value_of("design").map(|s| s.to_string()),
+                design_source: sub.value_of("design-source").map(|s| s.to_string()),
+                seed: seed(sub),
+                steps: conf.steps,
+                output: PathBuf::from(sub.value_of("output").unwrap()),
+                jobs: sub.value_of("jobs").unwrap().parse().unwrap(),
+            };
+            if let Err(err) = sweep::sweep(&spec, &opts) {
+                println!("error: {}", err);
+                process::exit(1);
+            }
+        },
+        ("verify-determinism", Some(sub)) => {
+            let mut conf = load_config(&config_path, sub, &[]);
+            conf.debug = true;
+            conf.seed = seed(sub);
+            let steps = conf.steps;
+            let design_source = DesignSource::parse(&conf.design_source);
+
+            println!("First run...");
+            let design = load_checked_design(&design_source, &conf);
+            let first = run::trace(new_simulation(design, conf.clone()), steps);
+
+            // A different number of threads,
+            // to catch any dependence on scheduling
+            println!("Second run...");
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(rayon::current_num_threads() + 1)
+                .build()
+                .unwrap();
+            let design = load_checked_design(&design_source, &conf);
+            let second = pool.install(|| run::trace(new_simulation(design, conf.clone()), steps));
+
+            match run::first_divergence(&first, &second) {
+                None => println!("Deterministic: {} steps identical with seed {}", steps, conf.seed),
+                Some(div) => {
+                    println!("error: runs diverged at step {} with seed {}, in: {}",
+                             div.step, conf.seed, div.parts.join(", "));
+                    process::exit(1);
+                }
+            }
+        },
+        ("events", Some(sub)) => {
+            query_events(sub);
+        },
+        ("replay", Some(sub)) => {
+            // The saved config includes the seed and steps
+            let run_dir = Path::new(sub.value_of("RUN").unwrap());
+            let mut conf = load_config(&run_dir.join("config.yaml"), sub, &[]);
+            conf.debug = true;
+            println!("{:?}", conf);
+
+            let design_source = DesignSource::parse(&conf.design_source);
+            let design = load_checked_design(&design_source, &conf);
+            let steps = conf.steps;
+            let output = run_headless(new_simulation(design, conf), steps, sub.value_of("events"));
+            save_run_data(&output, Path::new(sub.value_of("output").unwrap()));
+        },
+        _ => unreachable!()
+    }
+}
@@ -1,14 +1,18 @@
 use serde::Deserialize;
 use redis::{Commands, Connection};
 use strum_macros::{Display};
-use super::agent::{Tenant, DOMA};
+use std::str::FromStr;
+use super::acquisition::AcquisitionStrategy;
+use super::agent::{self, Employment, Tenant, DOMA};
+use super::governance::VotingRule;
+use super::household::{self, Household};
 use super::policy::Policy;
 use super::sim::Simulation;
 use super::city::{City, Unit};
 use rand::seq::SliceRandom;
 use serde_json::{json, Value};
-use std::collections::HashMap;
 use rand::rngs::StdRng;
+use std::collections::HashMap;
 use std::{thread, time};
 
 static COMMAND_INTERVAL_MS: u64 = 500;
@@ -29,8 +33,12 @@ enum Command {
     DOMAAdd(String, f32),           // player_id, amount
     DOMAPreach(String, f32, bool),  // player_id, amount, trigger
     DOMAConfigure(f32, f32, f32),   // p_dividend, p_rent_share, rent_income_limit
+    DOMAStrategy(String),           // acquisition strategy
+    DOMAVotingRule(String),         // voting rule
     RentFreeze(usize),              // months
     MarketTax(usize),               // months
+    JustCause(usize),               // months
+    RightToCounsel(usize),          // months
     Run(usize),                     // steps
     Reset,                          //
 }
@@ -56,16 +64,19 @@ impl PlayManager {
         }
     }
 
-    pub fn gen_player_tenant_pool(&self, tenants: &Vec<Tenant>, city: &City, size: usize) {
-        let mut rng = rand::thread_rng();
-        let tenants = tenants.choose_multiple(&mut rng, size);
+    pub fn gen_player_tenant_pool(&self, tenants: &[Tenant], city: &City, size: usize, rng: &mut StdRng) {
+        // Players are adults
+        let residents: Vec<&Tenant> = tenants.iter()
+            .filter(|t| !t.departed && t.employment != Employment::Child)
+            .collect();
+        let tenants = residents.choose_multiple(rng, size);
         let _: () = self.con.del("tenants").unwrap();
 
         // Move tenants into vacant units if necessary
         let vacant_units: Vec<&Unit> = city
             .units
             .iter()
-            .filter(|u| u.vacancies() > 0)
+            .filter(|u| u.vacant())
             .collect();
 
         for t in tenants {
@@ -101,6 +112,7 @@ impl PlayManager {
             let _: () = self.con.lpush("tenants", json!({
                 "id": t.id,
                 "income": t.income,
+                "employment": t.employment.to_string(),
                 "work": {
                     "pos": t.work,
                     "neighborhood": work_neighborhood
@@ -114,7 +126,7 @@ impl PlayManager {
         }
     }
 
-    pub fn sync_players(&self, tenants: &Vec<Tenant>, city: &City, doma: &DOMA) -> redis::RedisResult<()> {
+    pub fn sync_players(&self, tenants: &[Tenant], households: &[Household], city: &City, domas: &[DOMA]) -> redis::RedisResult<()> {
         for (player_id, &t_id) in &self.players {
             let tenant = &tenants[t_id];
             let mut adjusted_rent = None;
@@ -133,7 +145,7 @@ impl PlayManager {
                         Some(neighb) => Some(&neighb.name),
                         None => None
                     };
-                    tenant.desirability(unit, parcel)
+                    households[tenant.household].desirability(unit, parcel, tenants)
                 },
                 None => -1.
             };
@@ -147,11 +159,11 @@ impl PlayManager {
             self.con.set(key, json!({
                 "id": t_id,
                 "income": tenant.income,
-                "shares": match doma.shares.get(&t_id) {
-                    None => 0.,
-                    Some(s) => *s
-                },
+                "shares": domas.iter()
+                    .filter_map(|doma| doma.shares.get(&t_id))
+                    .sum::<f32>(),
                 "dividend": tenant.last_dividend,
+                "savings": tenant.savings,
                 "rent": adjusted_rent,
                 "work": {
                     "pos": tenant.work,
@@ -176,12 +188,12 @@ impl PlayManager {
     }
 
     pub fn sync_step(&self, step: usize, steps: usize) -> redis::RedisResult<()> {
-        self.con.set("step", step)?;
+        let _: () = self.con.set("step", step)?;
         self.con.set("step", step as f32/steps as f32)
     }
 
     fn set_status(&self, state: Status) -> redis::RedisResult<()> {
-        self.con.set("status", state.to_string().to_lowercase())?;
+        let _: () = self.con.set("status", state.to_string().to_lowercase())?;
         Ok(())
     }
 
@@ -199,14 +211,14 @@ impl PlayManager {
 
     pub fn reset(&mut self) -> redis::RedisResult<()> {
         self.players.clear();
-        self.con.del("game_step")?;
+        let _: () = self.con.del("game_step")?;
         self.con.del("cmds")
     }
 
-    pub fn wait_for_control(&mut self, sim: &mut Simulation, rng: &mut StdRng) -> Control {
+    pub fn wait_for_control(&mut self, sim: &mut Simulation) -> Control {
         let ms = time::Duration::from_millis(COMMAND_INTERVAL_MS);
         loop {
-            let control = self.process_commands(sim, rng);
+            let control = self.process_commands(sim);
             match control {
                 Some(ctrl) => return ctrl,
                 None => {
@@ -217,7 +229,7 @@ impl PlayManager {
         }
     }
 
-    pub fn process_commands(&mut self, sim: &mut Simulation, rng: &mut StdRng) -> Option<Control> {
+    pub fn process_commands(&mut self, sim: &mut Simulation) -> Option<Control> {
         let mut control = None;
         loop {
             let cmd_raw: Option<String> = self.con.lpop("cmds").unwrap();
@@ -228,21 +240,21 @@ impl PlayManager {
                         Command::SelectTenant(p_id, t_id) => {
                             println!("Player joined: {:?}", p_id);
                             self.players.insert(p_id, t_id);
-                            let tenant = &mut sim.tenants[t_id];
-                            tenant.player = true;
+                            sim.tenants[t_id].player = true;
 
-                            // Evict from existing unit, if any
-                            match tenant.unit {
-                                Some(_u_id) => {
-                                    let unit = &mut sim.city.units[_u_id];
-                                    unit.tenants.remove(&t_id);
-                                    tenant.unit = None;
-                                },
-                                None => {}
-                            }
+                            // Leave their household and
+                            // existing unit, if any
+                            let h_id = household::leave_household(
+                                &mut sim.households, t_id, &mut sim.tenants, &mut sim.city, &mut sim.events);
+                            sim.households[h_id].move_out(&mut sim.tenants, &mut sim.city);
 
                             // Reset tenant DOMA shares
-                            sim.doma.shares.insert(t_id, 0.);
+                            for doma in &mut sim.domas {
+                                doma.shares.remove(&t_id);
+                                doma.vesting.remove(&t_id);
+                            }
+                            let d_id = agent::choose_doma(&sim.domas, &sim.tenants[t_id], &sim.city);
+                            sim.domas[d_id].shares.insert(t_id, 0.);
                         },
                         Command::ReleaseTenant(p_id) => {
                             println!("Player left: {:?}", p_id);
@@ -262,17 +274,13 @@ impl PlayManager {
                             println!("Player {:?} moving to: {:?}", p_id, u_id);
                             match self.players.get(&p_id) {
                                 Some(&t_id) => {
-                                    let tenant = &mut sim.tenants[t_id];
-                                    match tenant.unit {
-                                        Some(_u_id) => {
-                                            let unit = &mut sim.city.units[_u_id];
-                                            unit.tenants.remove(&t_id);
-                                        },
-                                        None => {}
+                                    if sim.city.units[u_id].vacant() {
+                                        let household = &mut sim.households[sim.tenants[t_id].household];
+                                        household.move_out(&mut sim.tenants, &mut sim.city);
+                                        household.move_in(u_id, &mut sim.tenants, &mut sim.city);
+                                    } else {
+                                        println!("Unit {:?} is taken", u_id);
                                     }
-                                    let unit = &mut sim.city.units[u_id];
-                                    unit.tenants.insert(t_id);
-                                    tenant.unit = Some(u_id);
                                 },
                                 None => {}
                             }
@@ -281,7 +289,8 @@ impl PlayManager {
                             println!("Player {:?} adding {:?} to DOMA", p_id, amount);
                             match self.players.get(&p_id) {
                                 Some(&t_id) => {
-                                    sim.doma.add_funds(t_id, amount);
+                                    let d_id = agent::choose_doma(&sim.domas, &sim.tenants[t_id], &sim.city);
+                                    sim.domas[d_id].add_funds(&mut sim.tenants[t_id], amount, &mut sim.events);
                                 },
                                 None => {}
                             }
@@ -294,10 +303,16 @@ impl PlayManager {
                                     sim.conf.base_contribute_prob = f32::min(sim.conf.base_contribute_prob + amount, 0.75);
                                     sim.conf.base_contribute_percent = f32::min(sim.conf.base_contribute_percent + amount, 0.20);
                                     if trigger {
-                                        let infected = sim.social_graph.contagion(tenant_id, sim.conf.encounter_rate, sim.conf.transmission_rate, sim.conf.max_contagion_depth, rng);
+                                        let mut rng = sim.player_rng();
+                                        let infected = sim.social_graph.contagion(tenant_id, sim.conf.encounter_rate, sim.conf.transmission_rate, sim.conf.max_contagion_depth, &mut rng);
                                         for t_id in infected {
-                                            let t = &sim.tenants[t_id];
-                                            sim.doma.add_funds(t_id, sim.conf.base_contribute_percent * t.income);
+                                            if sim.tenants[t_id].departed {
+                                                continue;
+                                            }
+                                            let d_id = agent::choose_doma(&sim.domas, &sim.tenants[t_id], &sim.city);
+                                            let t = &mut sim.tenants[t_id];
+                                            let amount = sim.conf.base_contribute_percent * t.income;
+                                            sim.domas[d_id].add_funds(t, amount, &mut sim.events);
                                         }
                                     }
                                 },
@@ -306,17 +321,47 @@ impl PlayManager {
                         },
                         Command::DOMAConfigure(p_dividend, p_rent_share, rent_income_limit) => {
                             println!("Configuring DOMA {:?}, {:?}, {:?}", p_dividend, p_rent_share, rent_income_limit);
-                            sim.doma.p_reserves = 1.0 - p_dividend - sim.doma.p_expenses;
-                            sim.doma.p_rent_share = p_rent_share;
-                            sim.doma.rent_income_limit = Some(rent_income_limit);
+                            for doma in &mut sim.domas {
+                                doma.p_reserves = 1.0 - p_dividend - doma.p_expenses;
+                                doma.p_rent_share = p_rent_share;
+                                doma.rent_income_limit = Some(rent_income_limit);
+                            }
+                        },
+                        Command::DOMAStrategy(strategy) => {
+                            match AcquisitionStrategy::from_str(&strategy) {
+                                Ok(strategy) => {
+                                    println!("DOMA acquisition strategy: {:?}", strategy);
+                                    for doma in &mut sim.domas {
+                                        doma.strategy = strategy;
+                                    }
+                                },
+                                Err(_) => println!("Unknown acquisition strategy {:?}", strategy)
+                            }
+                        },
+                        Command::DOMAVotingRule(rule) => {
+                            match VotingRule::from_str(&rule) {
+                                Ok(rule) => {
+                                    println!("DOMA voting rule: {:?}", rule);
+                                    sim.conf.doma_voting_rule = rule;
+                                },
+                                Err(_) => println!("Unknown voting rule {:?}", rule)
+                            }
                         },
                         Command::RentFreeze(months) => {
                             println!("Rent Freeze for {:?} months", months);
-                            sim.policies.push((Policy::RentFreeze, months));
+                            sim.apply_policy(Policy::RentFreeze, months);
                         },
                         Command::MarketTax(months) => {
                             println!("Market Tax for {:?} months", months);
-                            sim.policies.push((Policy::MarketTax, months));
+                            sim.apply_policy(Policy::MarketTax, months);
+                        },
+                        Command::JustCause(months) => {
+                            println!("Just Cause for {:?} months", months);
+                            sim.apply_policy(Policy::JustCause, months);
+                        },
+                        Command::RightToCounsel(months) => {
+                            println!("Right to Counsel for {:?} months", months);
+                            sim.apply_policy(Policy::RightToCounsel, months);
                         },
                         Command::Run(n) => {
                             control = Some(Control::Run(n));
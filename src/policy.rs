@@ -1,5 +1,9 @@
-#[derive(PartialEq, Debug)]
+use serde::{Serialize, Deserialize};
+
+#[derive(PartialEq, Debug, Serialize, Deserialize)]
 pub enum Policy {
     RentFreeze,
-    MarketTax
+    MarketTax,
+    JustCause,
+    RightToCounsel
 }
@@ -0,0 +1,292 @@
+use super::agent::{AgentType, Employment, Tenant, ADULT_AGE};
+use super::config::Config;
+use super::events::{DepartureReason, EventKind};
+use super::household::{Household, HouseholdKind};
+use super::sim::Simulation;
+use fnv::FnvHashSet;
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use rand_distr::{LogNormal, Poisson};
+
+// Ages of newcomers, in years
+const MAX_NEWCOMER_AGE: usize = 50;
+
+// Parents' ages, in years
+const MAX_PARENT_AGE: usize = 45;
+
+// Annual chance of dying, by age in months
+fn mortality(age: usize, conf: &Config) -> f32 {
+    let years = age as f32 / 12.;
+    conf.mortality_base * (conf.mortality_growth * years).exp()
+}
+
+// Births, deaths, and people moving to and from the city.
+// Players and their households are left alone.
+impl Simulation {
+    pub fn update_population(&mut self, rng: &mut StdRng) {
+        let mut departed = FnvHashSet::default();
+        self.deaths(&mut departed, rng);
+        self.births(rng);
+        self.departures(&mut departed, rng);
+        self.arrivals(rng);
+        if !departed.is_empty() {
+            self.social_graph.remove_friends(&departed);
+        }
+    }
+
+    pub fn residents(&self) -> impl Iterator<Item=&Tenant> {
+        self.tenants.iter().filter(|t| !t.departed)
+    }
+
+    fn deaths(&mut self, departed: &mut FnvHashSet<usize>, rng: &mut StdRng) {
+        let mut died = Vec::new();
+        for tenant in self.residents() {
+            let roll: f32 = rng.gen();
+            if !tenant.player && roll < mortality(tenant.age, &self.conf) / 12. {
+                died.push(tenant.id);
+            }
+        }
+        for t_id in died {
+            let h_id = self.tenants[t_id].household;
+            let household = &mut self.households[h_id];
+            if let Some(u_id) = household.unit {
+                self.events.emit(EventKind::MovedOut { tenant: t_id, unit: u_id });
+            }
+            household.remove_member(t_id, &mut self.tenants, &mut self.city);
+
+            // Property, debts and savings go to the head of the
+            // household. Without one, the property is sold off.
+            if let Some(heir) = household.head(&self.tenants) {
+                for doma in &mut self.domas {
+                    for bond in &mut doma.bonds {
+                        if bond.holder == t_id {
+                            bond.holder = heir;
+                        }
+                    }
+                }
+                let units: Vec<usize> = self.tenants[t_id].units.drain(..).collect();
+                let mortgages: Vec<_> = self.tenants[t_id].mortgages.drain(..).collect();
+                let savings = self.tenants[t_id].savings;
+                self.tenants[t_id].savings = 0.;
+                for &u_id in &units {
+                    self.city.units[u_id].owner = (AgentType::Tenant, heir);
+                }
+                let heir = &mut self.tenants[heir];
+                heir.units.extend(units);
+                heir.mortgages.extend(mortgages);
+                heir.savings += savings;
+            } else {
+                self.settle_estate(t_id);
+            }
+            let household = &self.households[h_id];
+
+            // Shares are left to the rest of the household
+            let heirs = household.adults(&self.tenants);
+            for doma in &mut self.domas {
+                doma.bequeath(t_id, &heirs);
+            }
+            self.tenants[t_id].departed = true;
+            departed.insert(t_id);
+            self.events.emit(EventKind::Died { tenant: t_id });
+        }
+    }
+
+    fn births(&mut self, rng: &mut StdRng) {
+        let mut births = Vec::new();
+        for household in &self.households {
+            if household.kind != HouseholdKind::Family || household.player(&self.tenants) {
+                continue;
+            }
+            let parents = household.adults(&self.tenants);
+            let of_age = parents.iter()
+                .any(|&t_id| self.tenants[t_id].age < MAX_PARENT_AGE * 12);
+            let roll: f32 = rng.gen();
+            if parents.len() >= 2 && of_age && roll < self.conf.birth_rate {
+                births.push((household.id, parents));
+            }
+        }
+        for (h_id, parents) in births {
+            let id = self.tenants.len();
+            let wage = parents.iter().map(|&t_id| self.tenants[t_id].wage).sum::<f32>() / parents.len() as f32;
+            self.tenants.push(Tenant {
+                id,
+                household: h_id,
+                unit: None,
+                units: Vec::new(),
+                income: 0.,
+                wage,
+                employment: Employment::Child,
+                age: 0,
+                work: self.tenants[parents[0]].work,
+                last_dividend: 0.,
+                player: false,
+                departed: false,
+                evictions: 0,
+                savings: 0.,
+                last_rent: 0.,
+                mortgages: Vec::new(),
+            });
+            self.households[h_id].add_member(id, &mut self.tenants, &mut self.city);
+            self.social_graph.add_node();
+            for &parent in &parents {
+                self.social_graph.add_friend(id, parent);
+            }
+            self.events.emit(EventKind::Born { tenant: id, household: h_id });
+        }
+    }
+
+    // Households leave after too long without a home
+    // or paying too much of their income on rent,
+    // and sometimes for reasons of their own
+    fn departures(&mut self, departed: &mut FnvHashSet<usize>, rng: &mut StdRng) {
+        let mut leaving = Vec::new();
+        for household in &mut self.households {
+            if household.dissolved() || household.player(&self.tenants) {
+                continue;
+            }
+            match household.unit {
+                None => {
+                    household.months_homeless += 1;
+                    household.months_burdened = 0;
+                }
+                Some(u_id) => {
+                    let unit = &self.city.units[u_id];
+                    let costs = if household.owns_home(&self.city) {
+                        household.ownership_costs(unit, &self.tenants, &self.conf)
+                    } else {
+                        household.adjusted_rent(unit, &self.tenants)
+                    };
+                    let burden = costs / household.income(&self.tenants);
+                    household.months_homeless = 0;
+                    if burden > self.conf.cost_burden {
+                        household.months_burdened += 1;
+                    } else {
+                        household.months_burdened = 0;
+                    }
+                }
+            }
+
+            let roll: f32 = rng.gen();
+            if household.months_homeless >= self.conf.homeless_months_to_leave {
+                leaving.push((household.id, DepartureReason::Homelessness));
+            } else if household.months_burdened >= self.conf.burdened_months_to_leave {
+                leaving.push((household.id, DepartureReason::CostBurden));
+            } else if roll < self.conf.out_migration_rate {
+                leaving.push((household.id, DepartureReason::Other));
+            }
+        }
+
+        for (h_id, reason) in leaving {
+            let household = &mut self.households[h_id];
+            if let Some(u_id) = household.unit {
+                for &t_id in &household.members {
+                    self.events.emit(EventKind::MovedOut { tenant: t_id, unit: u_id });
+                }
+            }
+            household.move_out(&mut self.tenants, &mut self.city);
+            let members: Vec<usize> = household.members.drain(..).collect();
+            for t_id in members {
+                self.settle_estate(t_id);
+                self.tenants[t_id].departed = true;
+                // Shares not redeemed are forfeited
+                for doma in &mut self.domas {
+                    let price = doma.share_price(&self.city, &self.conf);
+                    doma.redeem(&mut self.tenants[t_id], self.conf.doma_redemption_share, price, &mut self.events);
+                    doma.shares.remove(&t_id);
+                    doma.vesting.remove(&t_id);
+                }
+                departed.insert(t_id);
+                self.events.emit(EventKind::LeftCity { tenant: t_id, reason });
+            }
+        }
+    }
+
+    // Newcomers arrive with jobs, looking for homes; more of them
+    // when there are vacancies and the local economy is doing well
+    fn arrivals(&mut self, rng: &mut StdRng) {
+        let n_vacant = self.city.units.iter().filter(|u| u.vacant()).count();
+        let n_neighborhoods = self.city.neighborhoods.len();
+        let activity = (0..n_neighborhoods)
+            .map(|neighb_id| self.city.activity(neighb_id, self.time, self.conf.desirability_stretch_factor))
+            .sum::<f32>() / n_neighborhoods as f32;
+        let expected = n_vacant as f32 * self.conf.in_migration_rate
+            * f32::max(0., 1. + self.conf.migration_job_sensitivity * activity);
+        if expected <= 0. {
+            return;
+        }
+        let n_arrivals = Poisson::new(expected as f64).unwrap().sample(rng);
+
+        let income_dist = LogNormal::new(self.design.city.income_mu, self.design.city.income_sigma).unwrap();
+        let (commercial, commercial_weights): (Vec<_>, Vec<_>) = self.city.commercial.iter()
+            .map(|(pos, &n)| (pos, n))
+            .unzip();
+        // Newcomers need somewhere to work
+        let work_dist = match WeightedIndex::new(commercial_weights) {
+            Ok(dist) => dist,
+            Err(_) => return
+        };
+        let kinds = [HouseholdKind::Single, HouseholdKind::Family, HouseholdKind::Roommates];
+        let kind_weights = [
+            self.conf.household_p_single,
+            self.conf.household_p_family,
+            self.conf.household_p_roommates,
+        ];
+        let kind_dist = WeightedIndex::new(kind_weights).unwrap();
+
+        for _ in 0..n_arrivals {
+            let kind = kinds[kind_dist.sample(rng)];
+            let (n_adults, n_children) = match kind {
+                HouseholdKind::Single => (1, 0),
+                HouseholdKind::Family => (2, rng.gen_range(0, 3)),
+                HouseholdKind::Roommates => (rng.gen_range(2, 4), 0),
+            };
+            let h_id = self.households.len();
+            let mut members = Vec::new();
+            for i in 0..n_adults + n_children {
+                let id = self.tenants.len();
+                let child = i >= n_adults;
+                let age = if child {
+                    rng.gen_range(0, ADULT_AGE * 12)
+                } else {
+                    rng.gen_range(ADULT_AGE * 12, MAX_NEWCOMER_AGE * 12)
+                };
+
+                // Newcomers' wages keep up with inflation
+                let wage = income_dist.sample(rng) * self.economy.price_level;
+                let mut tenant = Tenant {
+                    id,
+                    household: h_id,
+                    unit: None,
+                    units: Vec::new(),
+                    income: 0.,
+                    wage,
+                    employment: if child { Employment::Child } else { Employment::Employed },
+                    age,
+                    work: commercial[work_dist.sample(rng)],
+                    last_dividend: 0.,
+                    player: false,
+                    departed: false,
+                    evictions: 0,
+                    savings: 0.,
+                    last_rent: 0.,
+                    mortgages: Vec::new(),
+                };
+                tenant.income = tenant.current_income(&self.conf);
+                tenant.savings = tenant.income * self.conf.starting_savings;
+                self.tenants.push(tenant);
+                members.push(id);
+
+                self.social_graph.add_node();
+                let n_friends = if self.conf.friend_limit > 0 {
+                    rng.gen_range(0, self.conf.friend_limit)
+                } else {
+                    0
+                };
+                self.social_graph.add_random_friends(id, n_friends, rng);
+                self.events.emit(EventKind::ArrivedInCity { tenant: id });
+            }
+            self.households.push(Household::new(h_id, kind, members));
+        }
+    }
+}
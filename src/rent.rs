@@ -0,0 +1,128 @@
+use super::config::Config;
+use serde::{Serialize, Deserialize};
+use strum_macros::{Display, EnumString};
+
+// How a landlord sets rents
+#[derive(Display, EnumString, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum RentStrategy {
+    // Prices above where the market is headed,
+    // regardless of what current tenants can pay,
+    // and is slow to cut rents on vacant units
+    Aggressive,
+
+    // Follows the market, but only
+    // as far as current tenants can pay
+    Conservative,
+
+    // Prices below market to keep units occupied,
+    // with small, steady increases
+    LongTermHold,
+}
+
+// A landlord's view of the market
+// for a particular unit
+pub struct MarketEstimate {
+    // Estimated market rent for the unit
+    pub rent: f32,
+
+    // Expected change in market rents
+    // over the coming year, as a ratio
+    pub growth: f32,
+
+    // Share of comparable units that are vacant
+    pub vacancy: f32,
+}
+
+struct Params {
+    // Target rent relative to market rent
+    markup: f32,
+
+    // Price to where the market is headed,
+    // rather than where it is
+    follow_trend: bool,
+
+    // Limits on rent changes at renewal,
+    // as fractions of the current rent
+    max_decrease: f32,
+    max_increase: f32,
+
+    // Don't ask more than
+    // current tenants can pay
+    respect_wtp: bool,
+
+    // Additional discount for
+    // each month a unit is vacant
+    vacancy_cut: f32,
+}
+
+// Asking rents on vacant units
+// fall at most this much a month
+const MAX_MONTHLY_CUT: f32 = 0.1;
+
+// Vacancy discounts are capped at this
+const MAX_VACANCY_DISCOUNT: f32 = 0.5;
+
+impl RentStrategy {
+    fn params(&self, conf: &Config) -> Params {
+        match self {
+            RentStrategy::Aggressive => Params {
+                markup: 1.1,
+                follow_trend: true,
+                max_decrease: 0.,
+                max_increase: 0.15,
+                respect_wtp: false,
+                vacancy_cut: 0.01,
+            },
+            RentStrategy::Conservative => Params {
+                markup: 1.,
+                follow_trend: false,
+                max_decrease: 0.,
+                max_increase: conf.rent_increase_rate - 1.,
+                respect_wtp: true,
+                vacancy_cut: 0.02,
+            },
+            RentStrategy::LongTermHold => Params {
+                markup: 0.95,
+                follow_trend: false,
+                max_decrease: 0.05,
+                max_increase: 0.02,
+                respect_wtp: true,
+                vacancy_cut: 0.03,
+            },
+        }
+    }
+
+    fn target(&self, market: &MarketEstimate, params: &Params) -> f32 {
+        if params.follow_trend {
+            market.rent * market.growth * params.markup
+        } else {
+            market.rent * params.markup
+        }
+    }
+
+    // Rent for a lease renewal, where `wtp` is
+    // the most the current tenants are willing to pay
+    pub fn renewal_rent(&self, rent: f32, market: &MarketEstimate, wtp: f32, conf: &Config) -> f32 {
+        let params = self.params(conf);
+        let mut target = self.target(market, &params);
+        if params.respect_wtp {
+            target = f32::min(target, wtp);
+        }
+        clamp(target, rent * (1. - params.max_decrease), rent * (1. + params.max_increase))
+    }
+
+    // Asking rent for a vacant unit,
+    // discounted by the risk of it staying vacant
+    pub fn asking_rent(&self, rent: f32, market: &MarketEstimate, months_vacant: usize, conf: &Config) -> f32 {
+        let params = self.params(conf);
+        let risk = f32::min(
+            MAX_VACANCY_DISCOUNT,
+            market.vacancy + params.vacancy_cut * months_vacant as f32);
+        let target = self.target(market, &params) * (1. - risk);
+        clamp(target, rent * (1. - MAX_MONTHLY_CUT), rent * (1. + params.max_increase))
+    }
+}
+
+fn clamp(val: f32, min: f32, max: f32) -> f32 {
+    f32::min(f32::max(val, min), max)
+}
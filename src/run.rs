@@ -0,0 +1,97 @@
+use super::config::{self, Config};
+use super::design::{Design, DesignError};
+use super::sim::Simulation;
+use super::stats;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+pub struct RunOutput {
+    pub sim: Simulation,
+    pub init: Value,
+    pub history: Vec<Value>,
+}
+
+// Run a simulation for `conf.steps` steps without any redis syncing,
+// recording stats after every step
+pub fn run<F: FnMut(&Simulation)>(design: Design, conf: Config, on_step: F) -> Result<RunOutput, DesignError> {
+    let steps = conf.steps;
+    let sim = Simulation::new(design, conf)?;
+    Ok(run_from(sim, steps, on_step))
+}
+
+// Continue an existing simulation, e.g. one restored from a snapshot
+pub fn run_from<F: FnMut(&Simulation)>(mut sim: Simulation, steps: usize, mut on_step: F) -> RunOutput {
+    let init = stats::init_stats(&sim);
+    let mut history = Vec::with_capacity(steps);
+    for _ in 0..steps {
+        sim.step();
+        history.push(stats::stats(&sim));
+        on_step(&sim);
+    }
+    RunOutput {
+        sim,
+        init,
+        history,
+    }
+}
+
+// Where two runs first differed:
+// the step and which parts of the state
+#[derive(Debug)]
+pub struct Divergence {
+    pub step: usize,
+    pub parts: Vec<&'static str>,
+}
+
+// State hashes after setup and after every step
+pub fn trace(mut sim: Simulation, steps: usize) -> Vec<Vec<(&'static str, u64)>> {
+    let mut hashes = Vec::with_capacity(steps + 1);
+    hashes.push(sim.state_hashes());
+    for _ in 0..steps {
+        sim.step();
+        hashes.push(sim.state_hashes());
+    }
+    hashes
+}
+
+pub fn first_divergence(a: &[Vec<(&'static str, u64)>], b: &[Vec<(&'static str, u64)>]) -> Option<Divergence> {
+    for (step, (a, b)) in a.iter().zip(b).enumerate() {
+        let parts: Vec<&'static str> = a.iter().zip(b)
+            .filter(|(a, b)| a != b)
+            .map(|((name, _), _)| *name)
+            .collect();
+        if !parts.is_empty() {
+            return Some(Divergence {
+                step,
+                parts,
+            });
+        }
+    }
+    None
+}
+
+// Write a run's output and the config
+// that produced it to the given directory
+pub fn write_run_data(output: &RunOutput, dir: &Path) {
+    let sim = &output.sim;
+    let conf = &sim.conf;
+    let results = json!({
+        "init": output.init,
+        "history": output.history,
+        "meta": {
+            "seed": conf.seed,
+            "design": conf.design_id,
+            "design_source": conf.design_source,
+            "tenants": sim.tenants.len(),
+            "units": sim.city.units.len(),
+            "occupancy": sim.city.units.iter().fold(0, |acc, u| acc + u.occupancy),
+            "neighborhoods": sim.design.neighborhoods,
+        }
+    })
+    .to_string();
+
+    fs::create_dir_all(dir).unwrap();
+    fs::write(dir.join("output.json"), results).expect("Unable to write file");
+    config::save_config(conf, &dir.join("config.yaml"));
+}
@@ -0,0 +1,159 @@
+use super::agent::{AgentType, Employment, Tenant, DOMA};
+use super::city::City;
+use super::config::Config;
+use super::events::{EventKind, EventLog};
+
+// Buyers keep a year's income in savings
+// and only spend what's beyond that
+const BUYER_RESERVE_MONTHS: f32 = 12.;
+
+// How shares are earned, vest, change hands and are redeemed
+impl DOMA {
+    // Shares earned from rent vest after the vesting period;
+    // shares bought with contributions vest immediately
+    pub fn credit_shares(&mut self, tenant_id: usize, shares: f32, month: usize, conf: &Config) {
+        if conf.doma_vesting_months == 0 {
+            *self.shares.entry(tenant_id).or_insert(0.) += shares;
+        } else {
+            self.vesting.entry(tenant_id).or_default()
+                .push((shares, month + conf.doma_vesting_months));
+        }
+    }
+
+    pub fn vest(&mut self, month: usize) {
+        let mut vested = Vec::new();
+        for (&t_id, grants) in &mut self.vesting {
+            let amount: f32 = grants.iter().filter(|&&(_, m)| m <= month).map(|&(s, _)| s).sum();
+            grants.retain(|&(_, m)| m > month);
+            if amount > 0. {
+                vested.push((t_id, amount));
+            }
+        }
+        self.vesting.retain(|_, grants| !grants.is_empty());
+        for (t_id, amount) in vested {
+            *self.shares.entry(t_id).or_insert(0.) += amount;
+        }
+    }
+
+    pub fn unvested(&self) -> f32 {
+        self.vesting.values().flat_map(|grants| grants.iter().map(|&(s, _)| s)).sum()
+    }
+
+    // Net asset value per vested share, up to the price cap
+    pub fn share_price(&self, city: &City, conf: &Config) -> f32 {
+        let total_shares: f32 = self.shares.values().sum();
+        let nav = if total_shares > 0. {
+            (self.funds + self.portfolio_value(city) - self.debt()) / total_shares
+        } else {
+            1.
+        };
+        let price = f32::max(0., nav);
+        match conf.doma_share_price_cap {
+            Some(cap) => f32::min(price, cap),
+            None => price
+        }
+    }
+
+    // Buy back part of a member's vested shares, as far as
+    // funds allow. If any are bought back,
+    // their unvested shares are forfeited.
+    pub fn redeem(&mut self, tenant: &mut Tenant, fraction: f32, price: f32, events: &mut EventLog) {
+        let held = match self.shares.get(&tenant.id) {
+            Some(&held) => held,
+            None => return
+        };
+        if fraction <= 0. || price <= 0. {
+            return;
+        }
+        let payment = f32::min(held * fraction * price, f32::max(self.funds, 0.));
+        let shares = payment / price;
+        if shares <= 0. {
+            return;
+        }
+        self.vesting.remove(&tenant.id);
+        self.funds -= payment;
+        tenant.savings += payment;
+        let held = self.shares.get_mut(&tenant.id).unwrap();
+        *held -= shares;
+        if *held <= 0. {
+            self.shares.remove(&tenant.id);
+        }
+        events.emit(EventKind::DOMASharesRedeemed { doma: self.id, tenant: tenant.id, shares, amount: payment });
+    }
+
+    // Split a member's shares, vested or not, evenly among
+    // their heirs. Without heirs they're written off.
+    pub fn bequeath(&mut self, tenant_id: usize, heirs: &[usize]) {
+        if let Some(shares) = self.shares.remove(&tenant_id) {
+            for &heir in heirs {
+                *self.shares.entry(heir).or_insert(0.) += shares / heirs.len() as f32;
+            }
+        }
+        if let Some(grants) = self.vesting.remove(&tenant_id) {
+            for &heir in heirs {
+                let split = grants.iter().map(|&(s, m)| (s / heirs.len() as f32, m));
+                self.vesting.entry(heir).or_default().extend(split);
+            }
+        }
+    }
+
+    // Members short on cash sell shares to members and
+    // DOMA tenants with savings to spare, at the share price
+    pub fn trade_shares(&mut self, tenants: &mut [Tenant], city: &City, price: f32, events: &mut EventLog) {
+        if price <= 0. {
+            return;
+        }
+        let resident_adult = |t: &Tenant| !t.departed && !t.player && t.employment != Employment::Child;
+        let mut sellers: Vec<(usize, f32)> = self.shares.iter()
+            .filter(|&(&t_id, &held)| held > 0. && resident_adult(&tenants[t_id]))
+            .filter_map(|(&t_id, &held)| {
+                let tenant = &tenants[t_id];
+                let need = tenant.income - tenant.savings;
+                if need > 0. {
+                    Some((t_id, f32::min(held, need / price)))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        if sellers.is_empty() {
+            return;
+        }
+        let doma_id = self.id;
+        let mut buyers: Vec<(usize, f32)> = tenants.iter()
+            .filter(|t| resident_adult(t) && t.savings > t.income * BUYER_RESERVE_MONTHS)
+            .filter(|t| self.shares.contains_key(&t.id) || match t.unit {
+                Some(u_id) => city.units[u_id].owner == (AgentType::DOMA, doma_id),
+                None => false
+            })
+            .map(|t| (t.id, (t.savings - t.income * BUYER_RESERVE_MONTHS) / price))
+            .collect();
+
+        for seller in sellers.iter_mut() {
+            for buyer in buyers.iter_mut() {
+                if seller.1 <= 0. {
+                    break;
+                }
+                if buyer.1 <= 0. || buyer.0 == seller.0 {
+                    continue;
+                }
+                let shares = f32::min(seller.1, buyer.1);
+                seller.1 -= shares;
+                buyer.1 -= shares;
+                *self.shares.get_mut(&seller.0).unwrap() -= shares;
+                *self.shares.entry(buyer.0).or_insert(0.) += shares;
+                tenants[seller.0].savings += shares * price;
+                tenants[buyer.0].savings -= shares * price;
+                events.emit(EventKind::DOMASharesTraded {
+                    doma: doma_id, seller: seller.0, buyer: buyer.0, shares, price });
+            }
+        }
+
+        // Those who sold everything are no longer members
+        for &(t_id, _) in &sellers {
+            if self.shares[&t_id] <= 0. {
+                self.shares.remove(&t_id);
+            }
+        }
+    }
+}
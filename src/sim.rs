@@ -1,42 +1,117 @@
-use super::agent::{AgentType, Landlord, Tenant, DOMA};
+use super::agent::{self, AgentType, Employment, Landlord, Tenant, DOMA, DOMASpec, ADULT_AGE};
+use super::household::{self, Household, HouseholdDecision, HouseholdKind};
 use super::city::{City, Unit};
 use super::social::{SocialGraph};
 use super::config::Config;
 use super::policy::Policy;
-use super::design::Design;
+use super::rent::RentStrategy;
+use super::design::{Design, DesignError};
+use super::events::{Agent, EventKind, EventLog};
+use super::economy::Economy;
+use super::eviction::{EvictionReason, Protections};
+use super::finance::Mortgage;
 use noise::NoiseFn;
 use rand::distributions::WeightedIndex;
 use rand_distr::{LogNormal, Distribution};
 use rand::prelude::*;
 use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
+use rayon::prelude::*;
+use serde::{Serialize, Deserialize};
+use fnv::FnvHasher;
+use std::hash::Hasher;
 
+// All randomness after setup is drawn from streams derived
+// from the simulation's seed and the current step. Work that runs
+// in parallel gets one stream per agent, so results don't depend on
+// thread scheduling, and no RNG state has to be carried between steps.
+const STEP_STREAM: u64 = 0;
+const LANDLORD_STREAM: u64 = 1;
+const HOUSEHOLD_STREAM: u64 = 2;
+const PLAYER_STREAM: u64 = 3;
+const ECONOMY_STREAM: u64 = 4;
+const INCOME_STREAM: u64 = 5;
+const POPULATION_STREAM: u64 = 6;
+
+// Oldest starting age of tenants, in years
+const MAX_START_AGE: usize = 85;
+
+fn mix(x: u64) -> u64 {
+    // splitmix64 finalizer
+    let x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    let x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+pub fn stream_rng(seed: u64, time: usize, stream: u64, id: usize) -> StdRng {
+    let x = mix(mix(mix(seed ^ time as u64) ^ stream) ^ id as u64);
+    SeedableRng::seed_from_u64(x)
+}
+
+fn hash_state<T: Serialize>(val: &T) -> u64 {
+    let mut hasher = FnvHasher::default();
+    hasher.write(&bincode::serialize(val).unwrap());
+    hasher.finish()
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct Simulation {
     pub time: usize,
     pub city: City,
-    pub doma: DOMA,
+    pub domas: Vec<DOMA>,
     pub conf: Config,
     pub tenants: Vec<Tenant>,
+    pub households: Vec<Household>,
     pub landlords: Vec<Landlord>,
     pub policies: Vec<(Policy, usize)>,
     pub social_graph: SocialGraph,
     pub design: Design,
+    pub economy: Economy,
+
+    // This month's events
+    #[serde(skip)]
+    pub events: EventLog,
+
     transfers: Vec<(AgentType, usize, usize, f32)>,
 
+    // Seed for random streams
+    stream_seed: u64,
+
+    // Streams drawn by players this step
+    player_draws: usize,
+
     // For random iteration over populations
     landlord_order: Vec<usize>,
-    tenant_order: Vec<usize>,
+    household_order: Vec<usize>,
 }
 
 impl Simulation {
-    pub fn new(design: Design, config: Config, mut rng: &mut StdRng) -> Simulation {
+    // Fails if the city has no commercial floors
+    // for tenants to work on, see `Design::validate`
+    pub fn new(design: Design, config: Config) -> Result<Simulation, DesignError> {
+        let mut rng: StdRng = SeedableRng::seed_from_u64(config.seed);
+
         // Generate city from provided design
         println!("Creating city...");
         let mut city = City::new(&design, &mut rng);
 
         // Create landlords
+        let strategies = [
+            RentStrategy::Aggressive,
+            RentStrategy::Conservative,
+            RentStrategy::LongTermHold,
+        ];
+        let strategy_weights = [
+            config.landlord_p_aggressive,
+            config.landlord_p_conservative,
+            config.landlord_p_long_term_hold,
+        ];
+        let strategy_dist = WeightedIndex::new(strategy_weights).expect("invalid landlord strategy shares");
         let mut landlords: Vec<Landlord> = (0..design.city.landlords)
-            .map(|i| Landlord::new(i as usize, design.neighborhoods.len()))
+            .map(|i| {
+                let strategy = strategies[strategy_dist.sample(&mut rng)];
+                Landlord::new(i as usize, design.neighborhoods.len(), strategy, config.landlord_starting_cash)
+            })
             .collect();
 
         // Create tenants
@@ -48,53 +123,118 @@ impl Simulation {
             commercial.push(pos);
             commercial_weights.push(n);
         }
-        let work_dist = WeightedIndex::new(commercial_weights).unwrap();
-        let vacancies: Vec<usize> = city.units.iter().map(|u| u.id).collect();
-        let occupancy = city.units.iter().fold(0, |acc, u| acc + u.occupancy);
-        let population_size = (config.pop_p_occupancy * occupancy as f32).round() as usize;
+        let work_dist = WeightedIndex::new(commercial_weights).map_err(|_| DesignError::NoCommercial)?;
+        let mut households = household::form_households(&city, &mut rng, &config);
+        let population_size = households.iter().map(|h| h.size()).sum();
+
+        // Family members after the couple are their children
+        let mut children = vec![false; population_size];
+        for household in &households {
+            if household.kind == HouseholdKind::Family {
+                for &t_id in household.members.iter().skip(2) {
+                    children[t_id] = true;
+                }
+            }
+        }
         let mut tenants: Vec<Tenant> = (0..population_size)
             .map(|i| {
                 let tenant_id = i as usize;
-                let income = income_dist.sample(&mut rng);
+                let wage = income_dist.sample(&mut rng);
                 let work_pos = commercial[work_dist.sample(&mut rng)];
 
+                // Adults of all ages and their children,
+                // with unemployment at its long-run rate
+                let age = if children[tenant_id] {
+                    rng.gen_range(0, ADULT_AGE * 12)
+                } else {
+                    rng.gen_range(ADULT_AGE * 12, MAX_START_AGE * 12)
+                };
+                let p_unemployed = config.job_loss_rate / (config.job_loss_rate + config.job_find_rate);
+                let employment = if children[tenant_id] {
+                    Employment::Child
+                } else if age >= config.retirement_age * 12 {
+                    Employment::Retired
+                } else if rng.gen::<f32>() < p_unemployed {
+                    Employment::Unemployed
+                } else {
+                    Employment::Employed
+                };
+
                 let mut tenant = Tenant {
                     id: tenant_id,
+                    household: 0,
                     unit: None,
                     units: Vec::new(),
-                    income: income,
+                    income: 0.,
+                    wage,
+                    employment,
+                    age,
                     work: work_pos,
                     last_dividend: 0.,
                     player: false,
+                    departed: false,
+                    evictions: 0,
+                    savings: 0.,
+                    last_rent: 0.,
+                    mortgages: Vec::new(),
                 };
-
-                let lease_month = rng.gen_range(0, 11) as usize;
-                let (best_id, best_desirability) = vacancies.iter().fold((0, 0.), |acc, &u_id| {
-                    let u = &city.units[u_id];
-                    let p = &city.parcels.get(&u.pos).unwrap();
-                    if u.vacancies() <= 0 {
-                        acc
-                    } else {
-                        let desirability = tenant.desirability(u, p);
-                        if desirability > acc.1 {
-                            (u_id, desirability)
-                        } else {
-                            acc
-                        }
-                    }
-                });
-                tenant.unit = if best_desirability > 0. {
-                    let u = &mut city.units[best_id];
-                    u.tenants.insert(tenant_id);
-                    u.lease_month = lease_month;
-                    Some(best_id)
-                } else {
-                    None
-                };
-
+                tenant.income = tenant.current_income(&config);
+                tenant.savings = tenant.income * config.starting_savings;
                 tenant
             })
             .collect();
+        for household in &households {
+            for &t_id in &household.members {
+                tenants[t_id].household = household.id;
+            }
+        }
+
+        // Move households in. Vacancies are indexed by bedrooms,
+        // cheapest first, so each household only scores
+        // the units it fits in and can afford.
+        let max_occupancy = city.units.iter().map(|u| u.occupancy).max().unwrap_or(0);
+        let mut vacancies: Vec<Vec<usize>> = vec![Vec::new(); max_occupancy + 1];
+        for u in &city.units {
+            vacancies[u.occupancy].push(u.id);
+        }
+        for u_ids in vacancies.iter_mut() {
+            u_ids.sort_by(|&a, &b| city.units[a].rent.partial_cmp(&city.units[b].rent).unwrap());
+        }
+        let mut taken = vec![false; city.units.len()];
+        for household in &mut households {
+            let lease_month = rng.gen_range(0, 11) as usize;
+
+            // Households spend at most 1/3 of income on
+            // rent, after dividends; see `Household::desirability`
+            let max_rent = household.income(&tenants)/3. + household.dividends(&tenants);
+            let candidates: Vec<usize> = vacancies.iter()
+                .skip(household.bedrooms_needed())
+                .flat_map(|u_ids| u_ids.iter()
+                          .take_while(|&&u_id| f32::max(1., city.units[u_id].rent) <= max_rent))
+                .filter(|&&u_id| !taken[u_id])
+                .cloned()
+                .collect();
+
+            // Score them in parallel for the most desirable one;
+            // ties go to the lowest unit id
+            let (best_id, best_desirability) = {
+                let city = &city;
+                let household = &household;
+                let tenants = &tenants;
+                candidates.par_iter().map(|&u_id| {
+                    let u = &city.units[u_id];
+                    let p = &city.parcels.get(&u.pos).unwrap();
+                    (u_id, household.desirability(u, p, tenants))
+                }).reduce(|| (0, 0.), |a, b| {
+                    if b.1 > a.1 || (b.1 == a.1 && b.1 > 0. && b.0 < a.0) { b } else { a }
+                })
+            };
+            if best_desirability > 0. {
+                household.move_in(best_id, &mut tenants, &mut city);
+                city.units[best_id].lease_month = lease_month;
+                taken[best_id] = true;
+            }
+        }
 
         // Create social network
         println!("Creating social network...");
@@ -134,134 +274,629 @@ impl Simulation {
             }
         }
 
-        let doma = DOMA::new(
-            config.doma_starting_funds,
-            config.doma_p_rent_share,
-            config.doma_p_reserves,
-            config.doma_p_expenses,
-            config.doma_rent_income_limit,
-        );
+        // Landlords start out with their units partly mortgaged
+        for landlord in &mut landlords {
+            for &u_id in &landlord.units {
+                let principal = city.units[u_id].value * config.landlord_initial_ltv;
+                if principal > 0. {
+                    landlord.mortgages.push(Mortgage::new(u_id, principal, config.mortgage_rate, config.mortgage_term));
+                }
+            }
+        }
+
+        // As do owner-occupiers
+        for tenant in &mut tenants {
+            for &u_id in &tenant.units {
+                let principal = city.units[u_id].value * config.homeowner_initial_ltv;
+                if tenant.unit == Some(u_id) && principal > 0. {
+                    tenant.mortgages.push(Mortgage::new(u_id, principal, config.mortgage_rate, config.mortgage_term));
+                }
+            }
+        }
+
+        // The first DOMA is citywide,
+        // any others as specified
+        let citywide = DOMASpec {
+            starting_funds: config.doma_starting_funds,
+            neighborhoods: Vec::new(),
+            p_rent_share: None,
+            p_reserves: None,
+            rent_income_limit: None,
+            strategy: None,
+        };
+        let mut domas = vec![DOMA::new(0, &citywide, &config)];
+        for spec in &config.domas {
+            domas.push(DOMA::new(domas.len(), spec, &config));
+        }
+
+        let economy = Economy::new(design.city.price_to_rent_ratio, &config);
 
         let landlord_order = (0..landlords.len()).collect();
-        let tenant_order = (0..tenants.len()).collect();
+        let household_order = (0..households.len()).collect();
+        let stream_seed = rng.gen();
 
-        Simulation {
+        Ok(Simulation {
             time: 0,
             city: city,
             conf: config,
             landlords: landlords,
             tenants: tenants,
-            doma: doma,
+            households,
+            domas,
             design: design,
+            economy,
             policies: Vec::new(),
             social_graph: social_graph,
             landlord_order: landlord_order,
-            tenant_order: tenant_order,
-            transfers: Vec::new()
+            household_order,
+            transfers: Vec::new(),
+            events: EventLog::default(),
+            stream_seed,
+            player_draws: 0,
+        })
+    }
+
+    // Rebuild anything that isn't serialized
+    pub fn restore(&mut self) {
+        self.city.restore();
+        self.events.set_month(self.time);
+    }
+
+    // Hashes of each part of the state, for checking
+    // that runs with the same seed are identical
+    pub fn state_hashes(&self) -> Vec<(&'static str, u64)> {
+        vec![
+            ("time", hash_state(&self.time)),
+            ("city", hash_state(&self.city)),
+            ("tenants", hash_state(&self.tenants)),
+            ("households", hash_state(&self.households)),
+            ("landlords", hash_state(&self.landlords)),
+            ("economy", hash_state(&self.economy)),
+            ("domas", hash_state(&self.domas)),
+            ("social_graph", hash_state(&self.social_graph)),
+            ("policies", hash_state(&self.policies)),
+            ("conf", hash_state(&self.conf)),
+            ("rng", hash_state(&(self.stream_seed, self.player_draws))),
+            ("order", hash_state(&(&self.landlord_order, &self.household_order))),
+        ]
+    }
+
+    // Randomness for player actions between steps
+    pub fn player_rng(&mut self) -> StdRng {
+        self.player_draws += 1;
+        stream_rng(self.stream_seed, self.time, PLAYER_STREAM, self.player_draws)
+    }
+
+    // A household buys a unit to live in, putting down what it
+    // can out of members' savings and financing the rest
+    fn buy_home(&mut self, t_id: usize, unit_id: usize, price: f32) {
+        let household = &mut self.households[self.tenants[t_id].household];
+        let savings = household.savings(&self.tenants);
+        let down = f32::min(f32::max(savings, 0.), price * self.economy.down_payment(&self.conf));
+        if savings > 0. {
+            for &m_id in &household.members {
+                let member = &mut self.tenants[m_id];
+                member.savings -= down * f32::max(member.savings, 0.) / savings;
+            }
+        }
+        let tenant = &mut self.tenants[t_id];
+        tenant.units.push(unit_id);
+        if price > down {
+            tenant.mortgages.push(Mortgage::new(unit_id, price - down, self.economy.interest_rate, self.conf.mortgage_term));
+        }
+
+        // Move in, unless someone's
+        // renting the place by now
+        if self.city.units[unit_id].vacant() && household.members.contains(&t_id) {
+            if let Some(u_id) = household.unit {
+                for &m_id in &household.members {
+                    self.events.emit(EventKind::MovedOut { tenant: m_id, unit: u_id });
+                }
+                household.move_out(&mut self.tenants, &mut self.city);
+            }
+            household.move_in(unit_id, &mut self.tenants, &mut self.city);
+            for &m_id in &household.members {
+                self.events.emit(EventKind::MovedIn { tenant: m_id, unit: unit_id });
+            }
+        }
+    }
+
+    // Landlord with the most cash that can make the down payment,
+    // or failing that the DOMA if it has the funds
+    fn foreclosure_buyer(&self, owner: Agent, price: f32) -> Option<Agent> {
+        self.landlords.iter()
+            .filter(|l| (AgentType::Landlord, l.id) != owner && l.cash >= price * self.economy.down_payment(&self.conf))
+            .fold(None, |acc: Option<&Landlord>, l| match acc {
+                Some(best) if best.cash >= l.cash => Some(best),
+                _ => Some(l)
+            })
+            .map(|l| (AgentType::Landlord, l.id))
+            .or_else(|| self.domas.iter()
+                .filter(|d| (AgentType::DOMA, d.id) != owner && d.active() && d.funds >= price)
+                .fold(None, |acc: Option<&DOMA>, d| match acc {
+                    Some(best) if best.funds >= d.funds => Some(best),
+                    _ => Some(d)
+                })
+                .map(|d| (AgentType::DOMA, d.id)))
+    }
+
+    // Transfer a foreclosed unit to its buyer at auction
+    fn transfer_foreclosed(&mut self, unit_id: usize, buyer: Agent, price: f32) {
+        let (typ, id) = buyer;
+        match typ {
+            AgentType::Landlord => self.landlords[id].buy(unit_id, price, &self.economy, &self.conf),
+            _ => {
+                let doma = &mut self.domas[id];
+                doma.units.push(unit_id);
+                doma.funds -= price;
+            }
+        }
+        let unit = &mut self.city.units[unit_id];
+        unit.owner = buyer;
+        unit.value = price;
+        unit.offers.clear();
+    }
+
+    // Sell off the property of a tenant leaving the city or dying
+    // without heirs, on the same terms as a foreclosure auction.
+    // What's left after paying off the mortgage goes to their savings,
+    // and their DOMA bonds are repaid early.
+    pub fn settle_estate(&mut self, t_id: usize) {
+        for doma in &mut self.domas {
+            doma.repay_bonds(t_id, &mut self.tenants);
+        }
+        let owner = (AgentType::Tenant, t_id);
+        for unit_id in self.tenants[t_id].units.clone() {
+            let price = self.city.units[unit_id].value * self.conf.foreclosure_discount;
+
+            // Any landlord can finance the purchase
+            let buyer = self.foreclosure_buyer(owner, price)
+                .or_else(|| self.landlords.iter()
+                    .fold(None, |acc: Option<&Landlord>, l| match acc {
+                        Some(best) if best.cash >= l.cash => Some(best),
+                        _ => Some(l)
+                    })
+                    .map(|l| (AgentType::Landlord, l.id)));
+            if let Some(buyer) = buyer {
+                let tenant = &mut self.tenants[t_id];
+                let balance = tenant.remove_mortgage(unit_id);
+                tenant.savings += f32::max(0., price - balance);
+                tenant.units.retain(|&u_id| u_id != unit_id);
+                self.transfer_foreclosed(unit_id, buyer, price);
+                self.events.emit(EventKind::Sale {
+                    unit: unit_id, seller: owner, buyer, price });
+            }
+        }
+    }
+
+    // Owner-occupiers who fall too far behind on their mortgage
+    // lose their home; without a buyer at auction, the lender
+    // adds the arrears to the loan and restructures it
+    fn foreclose_homes(&mut self) {
+        for h_id in 0..self.households.len() {
+            let household = &self.households[h_id];
+            if !household.owns_home(&self.city) {
+                continue;
+            }
+            let unit_id = household.unit.unwrap();
+            let unit = &self.city.units[unit_id];
+            let owner_id = unit.owner.1;
+            let costs = household.ownership_costs(unit, &self.tenants, &self.conf);
+            let mortgaged = self.tenants[owner_id].mortgages.iter().any(|m| m.unit == unit_id);
+            if !mortgaged || unit.arrears < costs * self.conf.foreclosure_months as f32 {
+                continue;
+            }
+            let price = unit.value * self.conf.foreclosure_discount;
+            let arrears = unit.arrears;
+            let owner = (AgentType::Tenant, owner_id);
+            let buyer = self.foreclosure_buyer(owner, price);
+            let balance = self.tenants[owner_id].remove_mortgage(unit_id);
+            match buyer {
+                Some(buyer) => {
+                    let tenant = &mut self.tenants[owner_id];
+                    tenant.savings += f32::max(0., price - balance);
+                    tenant.units.retain(|&u_id| u_id != unit_id);
+                    self.transfer_foreclosed(unit_id, buyer, price);
+
+                    let household = &mut self.households[h_id];
+                    for &t_id in &household.members {
+                        self.events.emit(EventKind::MovedOut { tenant: t_id, unit: unit_id });
+                        self.events.emit(EventKind::BecameHomeless { tenant: t_id });
+                    }
+                    household.move_out(&mut self.tenants, &mut self.city);
+                }
+                None => {
+                    self.tenants[owner_id].mortgages.push(Mortgage::new(
+                        unit_id, balance + arrears, self.economy.interest_rate, self.conf.mortgage_term));
+                }
+            }
+            self.city.units[unit_id].arrears = 0.;
+            self.events.emit(EventKind::Foreclosure {
+                unit: unit_id, owner, buyer, price });
+        }
+    }
+
+    // Landlords who have been short on cash for too long lose
+    // their most indebted unit, auctioned at a discount to
+    // the landlord with the most cash who can afford it, or to DOMA
+    fn foreclose(&mut self) {
+        for i in 0..self.landlords.len() {
+            if self.landlords[i].months_negative < self.conf.foreclosure_months {
+                continue;
+            }
+            let unit_id = match self.landlords[i].most_indebted_unit() {
+                Some(u) => u,
+                None => continue
+            };
+            let price = self.city.units[unit_id].value * self.conf.foreclosure_discount;
+            let owner = (AgentType::Landlord, i);
+            let buyer = self.foreclosure_buyer(owner, price);
+            match buyer {
+                Some(buyer) => {
+                    self.landlords[i].foreclose(unit_id, price);
+                    self.transfer_foreclosed(unit_id, buyer, price);
+                }
+                None => self.landlords[i].restructure(unit_id, &self.economy, &self.conf)
+            }
+            self.events.emit(EventKind::Foreclosure {
+                unit: unit_id, owner, buyer, price });
+        }
+        for d_id in 0..self.domas.len() {
+            self.foreclose_doma(d_id);
+        }
+        self.foreclose_homes();
+    }
+
+    // Members who moved out of or were evicted from a DOMA's housing this month
+    // redeem some of their shares and forfeit unvested ones
+    fn redeem_on_move_out(&mut self) {
+        let moved_out: Vec<(usize, usize)> = self.events.events.iter()
+            .filter(|e| e.month == self.time)
+            .filter_map(|e| match e.kind {
+                EventKind::MovedOut { tenant, unit } |
+                EventKind::Evicted { tenant, unit, .. } => Some((tenant, unit)),
+                _ => None
+            })
+            .collect();
+        for (t_id, u_id) in moved_out {
+            let (typ, d_id) = self.city.units[u_id].owner;
+            if typ != AgentType::DOMA || self.tenants[t_id].departed {
+                continue;
+            }
+            let still_housed = match self.tenants[t_id].unit {
+                Some(home) => self.city.units[home].owner == (AgentType::DOMA, d_id),
+                None => false
+            };
+            if still_housed {
+                continue;
+            }
+            let doma = &mut self.domas[d_id];
+            if self.conf.doma_vesting_months > 0 {
+                doma.vesting.remove(&t_id);
+            }
+            if self.conf.doma_redemption_share > 0. {
+                let price = doma.share_price(&self.city, &self.conf);
+                doma.redeem(&mut self.tenants[t_id], self.conf.doma_redemption_share, price, &mut self.events);
+            }
+        }
+    }
+
+    // DOMAs too small after their first year merge into
+    // the largest DOMA buying where they do
+    fn merge_domas(&mut self) {
+        let min_members = self.conf.doma_merge_min_members;
+        if min_members == 0 || self.time < 12 {
+            return;
+        }
+        for i in 0..self.domas.len() {
+            let doma = &self.domas[i];
+            if !doma.active() || doma.shares.len() >= min_members {
+                continue;
+            }
+            let target = self.domas.iter()
+                .filter(|d| d.id != i && d.active() && d.shares.len() > doma.shares.len() && d.overlaps(doma))
+                .fold(None, |acc: Option<&DOMA>, d| match acc {
+                    Some(best) if best.shares.len() >= d.shares.len() => Some(best),
+                    _ => Some(d)
+                })
+                .map(|d| d.id);
+            if let Some(j) = target {
+                let (merged, into) = if i < j {
+                    let (left, right) = self.domas.split_at_mut(j);
+                    (&mut left[i], &mut right[0])
+                } else {
+                    let (left, right) = self.domas.split_at_mut(i);
+                    (&mut right[0], &mut left[j])
+                };
+                into.absorb(merged, &mut self.city, &mut self.events);
+            }
+        }
+    }
+
+    // DOMA in default loses its most indebted unit; without
+    // mortgages, its bondholders take shares for their bonds
+    fn foreclose_doma(&mut self, d_id: usize) {
+        if self.domas[d_id].months_negative < self.conf.foreclosure_months {
+            return;
+        }
+        let unit_id = match self.domas[d_id].most_indebted_unit() {
+            Some(u) => u,
+            None => {
+                self.domas[d_id].convert_bonds(&self.tenants, &mut self.events);
+                return;
+            }
+        };
+        let price = self.city.units[unit_id].value * self.conf.foreclosure_discount;
+        let owner = (AgentType::DOMA, d_id);
+        let buyer = self.foreclosure_buyer(owner, price);
+        match buyer {
+            Some(buyer) => {
+                self.domas[d_id].foreclose(unit_id, price);
+                self.transfer_foreclosed(unit_id, buyer, price);
+            }
+            None => self.domas[d_id].restructure(unit_id, &self.economy, &self.conf)
+        }
+        self.events.emit(EventKind::Foreclosure {
+            unit: unit_id, owner, buyer, price });
+    }
+
+    // Households pay rent for the month. Owners collect it
+    // from their units' `rent_paid` as they step.
+    fn collect_rent(&mut self) {
+        for unit in &mut self.city.units {
+            unit.rent_paid = 0.;
         }
+        for household in &self.households {
+            if household.dissolved() {
+                continue;
+            }
+            let payments = household.pay_rent(&mut self.tenants, &mut self.city, &self.conf);
+            if let Some(u_id) = household.unit {
+                let unit = &self.city.units[u_id];
+                match unit.owner {
+                    (AgentType::DOMA, d_id) => {
+                        for (t_id, amount) in payments {
+                            self.domas[d_id].credit_rent(t_id, amount, self.time, &self.conf);
+                        }
+                    }
+
+                    // Tenants renting out units they own
+                    (AgentType::Tenant, owner) => self.tenants[owner].savings += unit.rent_paid,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    // Remove households landlords have won eviction cases against
+    fn evict(&mut self, evictions: Vec<(usize, EvictionReason)>) {
+        for (unit_id, reason) in evictions {
+            let h_id = match self.city.units[unit_id].tenants.iter().next() {
+                Some(&t_id) => self.tenants[t_id].household,
+                None => continue
+            };
+            let household = &mut self.households[h_id];
+            for &t_id in &household.members {
+                self.tenants[t_id].evictions += 1;
+                self.events.emit(EventKind::Evicted { tenant: t_id, unit: unit_id, reason });
+                self.events.emit(EventKind::BecameHomeless { tenant: t_id });
+            }
+            household.move_out(&mut self.tenants, &mut self.city);
+        }
+    }
+
+    // Enact a policy for the given number of months
+    pub fn apply_policy(&mut self, policy: Policy, months: usize) {
+        self.policies.push((policy, months));
     }
 
-    pub fn step(&mut self, mut rng: &mut StdRng) {
+    pub fn step(&mut self) {
+        let mut rng = stream_rng(self.stream_seed, self.time, STEP_STREAM, 0);
+        self.events.drop_before(self.time);
+
+        // Rates, credit and inflation move
+        let mut economy_rng = stream_rng(self.stream_seed, self.time, ECONOMY_STREAM, 0);
+        self.economy.step(self.time, &mut economy_rng, &self.conf);
+
+        // Incomes change, in parallel
+        {
+            let activity: Vec<f32> = (0..self.city.neighborhoods.len())
+                .map(|neighb_id| self.city.activity(neighb_id, self.time, self.conf.desirability_stretch_factor))
+                .collect();
+            let city = &self.city;
+            let conf = &self.conf;
+            let inflation = self.economy.inflation;
+            let (seed, time) = (self.stream_seed, self.time);
+            let life_events: Vec<Option<EventKind>> = self.tenants.par_iter_mut().map(|tenant| {
+                if tenant.departed {
+                    return None;
+                }
+                let mut rng = stream_rng(seed, time, INCOME_STREAM, tenant.id);
+                let work_activity = match city.parcels.get(&tenant.work).unwrap().neighborhood {
+                    Some(neighb_id) => activity[neighb_id],
+                    None => 0.
+                };
+                tenant.update_income(work_activity, inflation, &mut rng, conf)
+            }).collect();
+            for event in life_events.into_iter().flatten() {
+                self.events.emit(event);
+            }
+        }
         let mut rent_freeze = false;
         let mut market_tax = false;
+        let mut protections = Protections {
+            just_cause: self.conf.just_cause,
+            right_to_counsel: self.conf.right_to_counsel,
+        };
         for (p, _) in &self.policies {
             match p {
                 Policy::RentFreeze => rent_freeze = true,
                 Policy::MarketTax => market_tax = true,
+                Policy::JustCause => protections.just_cause = true,
+                Policy::RightToCounsel => protections.right_to_counsel = true,
             }
         }
 
         for tenant in &mut self.tenants {
             self.transfers.extend(
-                tenant.check_purchase_offers(&mut self.city, self.design.city.price_to_rent_ratio),
+                tenant.check_purchase_offers(&mut self.city, self.economy.price_to_rent_ratio, &mut self.events),
             );
         }
         for landlord in &mut self.landlords {
             self.transfers.extend(
                 landlord
-                    .check_purchase_offers(&mut self.city, self.design.city.price_to_rent_ratio),
+                    .check_purchase_offers(&mut self.city, self.economy.price_to_rent_ratio, &mut self.events),
             );
         }
-        for (landlord_typ, landlord_id, unit_id, amount) in self.transfers.drain(..) {
+        for doma in &mut self.domas {
+            self.transfers.extend(
+                doma.check_purchase_offers(&mut self.city, &self.conf, &mut self.events),
+            );
+        }
+        let transfers: Vec<(AgentType, usize, usize, f32)> = self.transfers.drain(..).collect();
+        for (landlord_typ, landlord_id, unit_id, amount) in transfers {
             match landlord_typ {
                 AgentType::Landlord => {
                     let landlord = &mut self.landlords[landlord_id];
-                    landlord.units.push(unit_id);
+                    landlord.buy(unit_id, amount, &self.economy, &self.conf);
                 }
                 AgentType::DOMA => {
-                    self.doma.units.push(unit_id);
-                    self.doma.funds -= amount;
+                    self.domas[landlord_id].buy(unit_id, amount, &self.city, &mut self.tenants, &self.economy, &self.conf, &mut self.events);
                 }
-                _ => {}
+                AgentType::Tenant => self.buy_home(landlord_id, unit_id, amount)
             }
         }
 
+        // Update landlord market estimates
+        {
+            let city = &self.city;
+            let conf = &self.conf;
+            let (seed, time) = (self.stream_seed, self.time);
+            self.landlords.par_iter_mut().for_each(|landlord| {
+                let mut rng = stream_rng(seed, time, LANDLORD_STREAM, landlord.id);
+                landlord.update_estimates(city, &mut rng, conf);
+            });
+        }
+
+        self.collect_rent();
+
         self.landlord_order.shuffle(&mut rng);
+        let mut evictions = Vec::new();
         for &landlord_id in &self.landlord_order {
-            self.landlords[landlord_id].step(
+            evictions.extend(self.landlords[landlord_id].step(
                 &mut self.city,
                 self.time,
-                self.design.city.price_to_rent_ratio,
+                &self.economy,
                 rent_freeze,
                 market_tax,
+                protections,
                 &mut rng,
                 &self.conf,
-            );
+                &self.tenants,
+                &mut self.events,
+            ));
         }
+        self.evict(evictions);
+        self.foreclose();
+
+        // People are born, die, and move to and from the city
+        let mut population_rng = stream_rng(self.stream_seed, self.time, POPULATION_STREAM, 0);
+        self.update_population(&mut population_rng);
 
-        let mut vacant_units: Vec<usize> = self
+        // Households form and break up
+        let mut household_rng = stream_rng(self.stream_seed, self.time, HOUSEHOLD_STREAM, 0);
+        household::update_households(
+            &mut self.households, &mut self.tenants, &mut self.city,
+            &mut household_rng, &self.conf, &mut self.events);
+
+        // Include households formed since last step,
+        // here or by players
+        self.household_order.extend(self.household_order.len()..self.households.len());
+
+        let vacant_units: Vec<usize> = self
             .city
             .units
             .iter()
-            .filter(|u| u.vacancies() > 0)
+            .filter(|u| u.vacant())
             .map(|u| u.id)
             .collect();
 
-        self.tenant_order.shuffle(&mut rng);
-        for &tenant_id in &self.tenant_order {
-            let tenant = &mut self.tenants[tenant_id];
-            if !tenant.player {
-                tenant.step(
-                    &mut self.city,
-                    self.time,
-                    &mut vacant_units,
-                    &mut rng,
-                    &self.conf,
-                );
+        // Households decide in parallel...
+        let decisions: Vec<Option<HouseholdDecision>> = {
+            let city = &self.city;
+            let conf = &self.conf;
+            let economy = &self.economy;
+            let tenants = &self.tenants;
+            let vacant_units = &vacant_units;
+            let (seed, time) = (self.stream_seed, self.time);
+            self.households.par_iter().map(|household| {
+                if household.dissolved() || household.player(tenants) {
+                    None
+                } else {
+                    let mut rng = stream_rng(seed, time, HOUSEHOLD_STREAM, household.id);
+                    Some(household.decide(tenants, city, economy, time, vacant_units, &mut rng, conf))
+                }
+            }).collect()
+        };
+
+        // ...then act in random order
+        let mut vacated = Vec::new();
+        self.household_order.shuffle(&mut rng);
+        for &household_id in &self.household_order {
+            let household = &mut self.households[household_id];
+            if let Some(decision) = &decisions[household_id] {
+                household.act(decision, &mut self.tenants, &mut self.city, self.time, &mut vacated, &self.conf, &mut self.events);
 
                 // Word-of-mouth/contagion
-                let roll: f32 = rng.gen();
-                if roll < self.conf.base_contribute_prob {
-                    self.doma.add_funds(tenant_id, self.conf.base_contribute_percent * tenant.income);
-                    let infected = self.social_graph.contagion(tenant_id, self.conf.encounter_rate, self.conf.transmission_rate, self.conf.max_contagion_depth, &mut rng);
-                    for t_id in infected {
-                        let t = &self.tenants[t_id];
-                        self.doma.add_funds(t_id, self.conf.base_contribute_percent * t.income);
+                for &tenant_id in &household.members {
+                    let roll: f32 = rng.gen();
+                    if roll < self.conf.base_contribute_prob {
+                        let d_id = agent::choose_doma(&self.domas, &self.tenants[tenant_id], &self.city);
+                        let tenant = &mut self.tenants[tenant_id];
+                        let amount = self.conf.base_contribute_percent * tenant.income;
+                        self.domas[d_id].add_funds(tenant, amount, &mut self.events);
+                        let infected = self.social_graph.contagion(tenant_id, self.conf.encounter_rate, self.conf.transmission_rate, self.conf.max_contagion_depth, &mut rng);
+                        for t_id in infected {
+                            if self.tenants[t_id].departed {
+                                continue;
+                            }
+                            let d_id = agent::choose_doma(&self.domas, &self.tenants[t_id], &self.city);
+                            let t = &mut self.tenants[t_id];
+                            let amount = self.conf.base_contribute_percent * t.income;
+                            self.domas[d_id].add_funds(t, amount, &mut self.events);
+                        }
                     }
                 }
             }
         }
 
+        self.redeem_on_move_out();
+
         if self.time % 12 == 0 {
-            // Appraise
-            for unit_ids in &self.city.units_by_neighborhood {
+            // Appraise, estimating values for
+            // each neighborhood in parallel
+            let base_appreciation = self.economy.appreciation(&self.conf);
+            let city = &self.city;
+            let mean_values_per_area: Vec<f32> = city.units_by_neighborhood.par_iter().map(|unit_ids| {
                 let units: Vec<&Unit> = unit_ids
                     .iter()
-                    .map(|&u_id| &self.city.units[u_id])
+                    .map(|&u_id| &city.units[u_id])
                     .collect();
                 let sold: Vec<&Unit> = units.iter().filter(|u| u.recently_sold).cloned().collect();
-                let mean_value_per_area = if sold.len() == 0 {
+                if sold.is_empty() {
                     units.iter().fold(0., |acc, u| {
-                        acc + (u.value_per_area() * self.conf.base_appreciation)
+                        acc + (u.value_per_area() * base_appreciation)
                     }) / units.len() as f32
                 } else {
                     sold.iter().fold(0., |acc, u| {
-                        acc + (u.value_per_area() * self.conf.base_appreciation)
+                        acc + (u.value_per_area() * base_appreciation)
                     }) / sold.len() as f32
-                };
+                }
+            }).collect();
 
+            for (unit_ids, mean_value_per_area) in self.city.units_by_neighborhood.iter().zip(mean_values_per_area) {
                 for &u_id in unit_ids {
-                    let mut unit = &mut self.city.units[u_id];
+                    let unit = &mut self.city.units[u_id];
                     if !unit.recently_sold {
                         unit.value = mean_value_per_area * unit.area;
                     }
@@ -270,7 +905,22 @@ impl Simulation {
             }
         }
 
-        self.doma.step(&mut self.city, &mut self.tenants, &mut rng);
+        let interval = self.conf.doma_vote_interval;
+        for doma in &mut self.domas {
+            if !doma.active() {
+                continue;
+            }
+            doma.vest(self.time);
+            if self.conf.doma_share_trading {
+                let price = doma.share_price(&self.city, &self.conf);
+                doma.trade_shares(&mut self.tenants, &self.city, price, &mut self.events);
+            }
+            if interval > 0 && self.time > 0 && self.time.is_multiple_of(interval) {
+                doma.hold_vote(&self.city, &self.tenants, &self.conf, &mut self.events);
+            }
+            doma.step(&mut self.city, &mut self.tenants, &self.economy, &mut rng, &self.conf, &mut self.events);
+        }
+        self.merge_domas();
 
         // Desirability changes, random walk
         for (neighb_id, parcel_ids) in self.city.residential_parcels_by_neighborhood.iter().enumerate() {
@@ -302,5 +952,7 @@ impl Simulation {
         }).collect();
 
         self.time += 1;
+        self.player_draws = 0;
+        self.events.set_month(self.time);
     }
 }
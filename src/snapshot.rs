@@ -0,0 +1,83 @@
+use super::sim::Simulation;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+// Snapshots are a short header (magic bytes and format version)
+// followed by the bincode-encoded simulation.
+// Bump the version whenever the simulation's state changes shape,
+// older snapshots are then rejected rather than misread.
+pub const SNAPSHOT_VERSION: u32 = 16;
+const MAGIC: &[u8; 8] = b"DOMASNAP";
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    NotASnapshot,
+    VersionMismatch { found: u32 },
+    Encoding(bincode::Error),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Io(err) => write!(f, "{}", err),
+            SnapshotError::NotASnapshot => write!(f, "not a simulation snapshot"),
+            SnapshotError::VersionMismatch { found } =>
+                write!(f, "snapshot is version {}, expected {}", found, SNAPSHOT_VERSION),
+            SnapshotError::Encoding(err) => write!(f, "could not decode snapshot: {}", err),
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> SnapshotError {
+        SnapshotError::Io(err)
+    }
+}
+
+impl From<bincode::Error> for SnapshotError {
+    fn from(err: bincode::Error) -> SnapshotError {
+        SnapshotError::Encoding(err)
+    }
+}
+
+// Save the complete simulation state.
+// Writes to a temporary file first so an interrupted
+// save doesn't clobber an existing snapshot.
+pub fn save_snapshot(sim: &Simulation, path: &Path) -> Result<(), SnapshotError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    {
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&SNAPSHOT_VERSION.to_le_bytes())?;
+        bincode::serialize_into(&mut writer, sim)?;
+        writer.flush()?;
+    }
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+// Load a simulation from a snapshot;
+// stepping it continues exactly where the saved one left off
+pub fn load_snapshot(path: &Path) -> Result<Simulation, SnapshotError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic).map_err(|_| SnapshotError::NotASnapshot)?;
+    if &magic != MAGIC {
+        return Err(SnapshotError::NotASnapshot);
+    }
+    let mut version = [0; 4];
+    reader.read_exact(&mut version).map_err(|_| SnapshotError::NotASnapshot)?;
+    let version = u32::from_le_bytes(version);
+    if version != SNAPSHOT_VERSION {
+        return Err(SnapshotError::VersionMismatch { found: version });
+    }
+    let mut sim: Simulation = bincode::deserialize_from(reader)?;
+    sim.restore();
+    Ok(sim)
+}
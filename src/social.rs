@@ -1,20 +1,21 @@
 use fnv::FnvHashSet;
-use petgraph::csr::Csr;
 use rand::prelude::*;
 use rand::rngs::StdRng;
+use serde::{Serialize, Deserialize};
 
+// Each tenant's friends, sorted by id
+#[derive(Serialize, Deserialize)]
 pub struct SocialGraph {
-    graph: Csr<usize, ()>,
+    friends: Vec<Vec<u32>>,
 }
 
 impl SocialGraph {
     pub fn new(n: usize, friend_limit: usize, mut rng: &mut StdRng) -> SocialGraph {
-        let graph = Csr::<usize, ()>::with_nodes(n);
         let mut social_graph = SocialGraph {
-            graph: graph
+            friends: vec![Vec::new(); n]
         };
         for i in 0..n {
-            let n_friends = rng.gen_range(0, friend_limit) as usize;
+            let n_friends = if friend_limit > 0 { rng.gen_range(0, friend_limit) } else { 0 };
             social_graph.add_random_friends(i, n_friends, &mut rng);
         }
 
@@ -25,8 +26,33 @@ impl SocialGraph {
         // There may be some redundancy here,
         // which we accept for simplicity
         for _ in 0..n {
-            let friend = rng.gen_range(0, self.graph.node_count());
-            self.graph.add_edge(id as u32, friend as u32, ());
+            let friend = rng.gen_range(0, self.friends.len());
+            self.add_friend(id, friend);
+        }
+    }
+
+    // Add a node for a new tenant, returning its id
+    pub fn add_node(&mut self) -> usize {
+        self.friends.push(Vec::new());
+        self.friends.len() - 1
+    }
+
+    pub fn add_friend(&mut self, id: usize, friend: usize) {
+        let friends = &mut self.friends[id];
+        if let Err(i) = friends.binary_search(&(friend as u32)) {
+            friends.insert(i, friend as u32);
+        }
+    }
+
+    // Drop all friendships of the given tenants, e.g. those who
+    // have left the city. Their nodes stay so ids don't shift.
+    pub fn remove_friends(&mut self, ids: &FnvHashSet<usize>) {
+        for (id, friends) in self.friends.iter_mut().enumerate() {
+            if ids.contains(&id) {
+                friends.clear();
+            } else {
+                friends.retain(|f| !ids.contains(&(*f as usize)));
+            }
         }
     }
 
@@ -34,13 +60,14 @@ impl SocialGraph {
         let mut depth = 0;
         let mut nodes = FnvHashSet::default();
         let mut next = FnvHashSet::default();
-        let mut fringe = FnvHashSet::default();
 
-        fringe.insert(start_id);
+        // Visited in id order, so random draws
+        // don't depend on hash set iteration order
+        let mut fringe = vec![start_id];
         while fringe.len() > 0 {
             next.clear();
-            for id in fringe.drain() {
-                let neighbs = self.graph.neighbors_slice(id as u32);
+            for id in fringe.drain(..) {
+                let neighbs = &self.friends[id];
                 for n in neighbs {
                     let n = *n as usize;
 
@@ -60,6 +87,7 @@ impl SocialGraph {
                 }
             }
             fringe = next.iter().cloned().collect();
+            fringe.sort();
             depth += 1;
             if depth >= max_depth {
                 break;
@@ -1,6 +1,9 @@
-use super::agent::AgentType;
+use super::agent::{AgentType, Employment, Tenant, DOMA};
+use super::events::{DepartureReason, EventKind};
+use super::household::{Household, HouseholdKind};
 use super::sim::Simulation;
 use serde_json::{json, Value};
+use rayon::prelude::*;
 use std::collections::{HashMap, HashSet};
 
 pub fn init_stats(sim: &Simulation) -> Value {
@@ -20,11 +23,140 @@ pub fn init_stats(sim: &Simulation) -> Value {
     })
 }
 
+// Totals for a single neighborhood,
+// computed in parallel and then combined
+#[derive(Default)]
+struct NeighborhoodTotals {
+    n_vacant: f32,
+    n_housed: f32,
+    n_parcels: f32,
+
+    // Housed tenants with an income, e.g. not children;
+    // rent burdens are only measured for them
+    n_earners: f32,
+    n_affordable: f32,
+    rent: f32,
+    rent_per_area: f32,
+    rent_per_tenant: f32,
+    adjusted_rent_per_area: f32,
+    months_vacant: f32,
+    value_per_area: f32,
+    value: f32,
+    min_value: f32,
+    condition: f32,
+    price_to_rent_ratio: f32,
+    rent_income_ratio: f32,
+    offers: f32,
+    desirability: f32,
+    owners: HashSet<(AgentType, usize)>,
+    landlord_data: HashMap<usize, (f32, f32)>,
+    doma_data: HashMap<usize, (f32, f32)>,
+    stats: Option<Value>,
+}
+
+fn neighborhood_totals(sim: &Simulation, neighb_id: usize, unit_ids: &Vec<usize>) -> NeighborhoodTotals {
+    let mut totals = NeighborhoodTotals {
+        min_value: 1. / 0.,
+        ..Default::default()
+    };
+    if unit_ids.is_empty() {
+        return totals;
+    }
+
+    let mut nei_n_doma = 0;
+    let mut nei_n_tenants = 0;
+
+    for &unit_id in unit_ids {
+        let unit = &sim.city.units[unit_id];
+        let value = unit.value;
+        totals.offers += unit.offers.len() as f32;
+        totals.rent += unit.rent;
+        totals.rent_per_area += unit.rent_per_area();
+        totals.months_vacant += unit.months_vacant as f32;
+        totals.value_per_area += value / unit.area;
+        totals.value += value;
+        totals.condition += unit.condition;
+        totals.price_to_rent_ratio += if unit.rent == 0. {
+            0.
+        } else {
+            value / (unit.rent * 12.)
+        };
+        if value < totals.min_value {
+            totals.min_value = value;
+        }
+
+        if unit.vacant() {
+            totals.n_vacant += 1.;
+        }
+
+        let mut rent_discount = 0.;
+        // Rent split among the household
+        let rent_per_tenant = unit.rent / std::cmp::max(1, unit.tenants.len()) as f32;
+        for &t_id in &unit.tenants {
+            let tenant = &sim.tenants[t_id];
+            rent_discount += tenant.last_dividend;
+            totals.rent_per_tenant += rent_per_tenant;
+            if tenant.income <= 0. {
+                continue;
+            }
+            totals.n_earners += 1.;
+            totals.rent_income_ratio += rent_per_tenant / tenant.income;
+            if (rent_per_tenant-tenant.last_dividend) / tenant.income <= 0.3 {
+                totals.n_affordable += 1.;
+            }
+        }
+        let mean_adj_rent_per_area = f32::max(0., unit.rent - f32::min(unit.rent, rent_discount)) / unit.area;
+        totals.adjusted_rent_per_area += mean_adj_rent_per_area;
+        totals.n_housed += unit.tenants.len() as f32;
+        nei_n_tenants += unit.tenants.len();
+
+        totals.owners.insert(unit.owner);
+        match unit.owner.0 {
+            AgentType::Landlord => {
+                let data = totals.landlord_data.entry(unit.owner.1).or_insert((0., 0.));
+                data.0 += unit.condition;
+                data.1 += mean_adj_rent_per_area;
+            },
+            AgentType::DOMA => {
+                let data = totals.doma_data.entry(unit.owner.1).or_insert((0., 0.));
+                data.0 += unit.condition;
+                data.1 += mean_adj_rent_per_area;
+                nei_n_doma += 1;
+            }
+            _ => {}
+        }
+    }
+
+    let nei_n_units = unit_ids.len() as f32;
+    let parcels = &sim.city.residential_parcels_by_neighborhood[neighb_id];
+    totals.n_parcels = parcels.len() as f32;
+    totals.desirability = parcels
+        .iter()
+        .fold(0., |acc, pos| acc + sim.city.parcels.get(pos).unwrap().desirability);
+
+    totals.stats = Some(json!({
+        "percent_vacant": totals.n_vacant/nei_n_units,
+        "mean_rent": totals.rent/nei_n_units,
+        "mean_rent_per_tenant": totals.rent_per_tenant/(nei_n_tenants as f32),
+        "mean_rent_per_area": totals.rent_per_area/nei_n_units,
+        "mean_adjusted_rent_per_area": totals.adjusted_rent_per_area/nei_n_units,
+        "mean_value_per_area": totals.value_per_area/nei_n_units,
+        "mean_months_vacant": totals.months_vacant/nei_n_units,
+        "mean_rent_income_ratio": if totals.n_earners > 0. {
+            totals.rent_income_ratio/totals.n_earners
+        } else { 0. },
+        "mean_desirability": totals.desirability/parcels.len() as f32,
+        "doma_units": nei_n_doma
+    }));
+    totals
+}
+
 pub fn stats(sim: &Simulation) -> Value {
     let n_units = sim.city.units.len() as f32;
     let mut n_housed = 0.;
     let mut n_vacant = 0.;
     let mut n_parcels = 0.;
+    let mut n_earners = 0.;
     let mut n_affordable = 0.;
     let mut mean_rent = 0.;
     let mut mean_rent_per_area = 0.;
@@ -41,116 +173,101 @@ pub fn stats(sim: &Simulation) -> Value {
     let mut mean_desirability = 0.;
     let mut unique_landlords = HashSet::new();
     let mut landlord_data = HashMap::new();
-    let mut doma_data = (0., 0.);
-    let mean_income = sim.tenants.iter().fold(0., |acc, t| acc + t.income)/sim.tenants.len() as f32;
+    let mut doma_data = HashMap::new();
+    let residents: Vec<&Tenant> = sim.residents().collect();
+    let population = residents.len() as f32;
+    let mean_income = residents.iter().fold(0., |acc, t| acc + t.income)/population;
+    let n_with_employment = |employment: Employment| {
+        residents.iter().filter(|t| t.employment == employment).count() as f32
+    };
+    let n_employed = n_with_employment(Employment::Employed);
+    let n_unemployed = n_with_employment(Employment::Unemployed);
+    let n_retired = n_with_employment(Employment::Retired);
+    let n_children = n_with_employment(Employment::Child);
 
-    let mut neighborhood_stats = HashMap::new();
-    for (neighb_id, unit_ids) in sim.city.units_by_neighborhood.iter().enumerate() {
-        if unit_ids.len() == 0 {
-            continue;
-        }
+    let n_occupied = sim.city.units.iter().filter(|u| !u.vacant()).count() as f32;
+    let n_in_arrears = sim.city.units.iter().filter(|u| u.arrears > 0.).count() as f32;
+    let total_arrears: f32 = sim.city.units.iter().map(|u| u.arrears).sum();
+    let n_pending_evictions = sim.city.units.iter().filter(|u| u.eviction.is_some()).count();
+    let n_ever_evicted = residents.iter().filter(|t| t.evictions > 0).count() as f32;
 
-        let mut nei_n_doma = 0;
-        let mut nei_n_vacant = 0.;
-        let mut nei_n_tenants = 0;
-        let mut nei_mean_rent = 0.;
-        let mut nei_mean_rent_per_area = 0.;
-        let mut nei_mean_rent_per_tenant = 0.;
-        let mut nei_mean_adjusted_rent_per_area = 0.;
-        let mut nei_mean_value_per_area = 0.;
-        let mut nei_mean_months_vacant = 0.;
-        let mut nei_mean_rent_income_ratio = 0.;
-
-        for &unit_id in unit_ids {
-            let unit = &sim.city.units[unit_id];
-            let value = unit.value;
-            mean_offers += unit.offers.len() as f32;
-            nei_mean_rent += unit.rent;
-            nei_mean_rent_per_area += unit.rent_per_area();
-            nei_mean_months_vacant += unit.months_vacant as f32;
-            nei_mean_value_per_area += value / unit.area;
-            mean_value += value;
-            mean_condition += unit.condition;
-            mean_price_to_rent_ratio += if unit.rent == 0. {
-                0.
-            } else {
-                value / (unit.rent * 12.)
-            };
-            if value < min_value {
-                min_value = value;
-            }
-
-            if unit.vacant() {
-                nei_n_vacant += 1.;
-            }
+    // Savings of adults; children have no income to save
+    let mut savings: Vec<f32> = residents.iter()
+        .filter(|t| t.employment != Employment::Child)
+        .map(|t| t.savings)
+        .collect();
+    let total_savings: f32 = savings.iter().sum();
+    savings.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let median_savings = if !savings.is_empty() { savings[savings.len()/2] } else { 0. };
+    let n_without_savings = savings.iter().filter(|&&s| s <= 0.).count() as f32;
+    let rent_paid: f32 = sim.city.units.iter().map(|u| u.rent_paid).sum();
+    let rent_burdens: Vec<f32> = residents.iter()
+        .filter(|t| t.unit.is_some() && t.income > 0.)
+        .map(|t| t.last_rent / t.income)
+        .collect();
+    let mean_rent_burden = if !rent_burdens.is_empty() {
+        rent_burdens.iter().sum::<f32>() / rent_burdens.len() as f32
+    } else {
+        0.
+    };
+    let households: Vec<&Household> = sim.households.iter().filter(|h| !h.dissolved()).collect();
+    let n_households = households.len() as f32;
+    let p_household_kind = |kind: HouseholdKind| {
+        households.iter().filter(|h| h.kind == kind).count() as f32 / n_households
+    };
 
-            let mut rent_discount = 0.;
-            // let rent_per_tenant = unit.rent / unit.tenants.len() as f32;
-            let rent_per_tenant = unit.rent / unit.occupancy as f32;
-            for &t_id in &unit.tenants {
-                let tenant = &sim.tenants[t_id];
-                rent_discount += tenant.last_dividend;
-                nei_mean_rent_income_ratio += rent_per_tenant / tenant.income;
-                nei_mean_rent_per_tenant += rent_per_tenant;
-                if (rent_per_tenant-tenant.last_dividend) / tenant.income <= 0.3 {
-                    n_affordable += 1.;
-                }
-            }
-            let mean_adj_rent_per_area = f32::max(0., unit.rent - f32::min(unit.rent, rent_discount)) / unit.area;
-            nei_mean_adjusted_rent_per_area += mean_adj_rent_per_area;
-            n_housed += unit.tenants.len() as f32;
-            nei_n_tenants += unit.tenants.len();
-
-            unique_landlords.insert(unit.owner);
-            match unit.owner.0 {
-                AgentType::Landlord => {
-                    let data = landlord_data.entry(unit.owner.1).or_insert((0., 0.));
-                    data.0 += unit.condition;
-                    data.1 += mean_adj_rent_per_area;
-                },
-                AgentType::DOMA => {
-                    doma_data.0 += unit.condition;
-                    doma_data.1 += mean_adj_rent_per_area;
-                    nei_n_doma += 1;
-                }
-                _ => {}
-            }
-        }
+    // Tenure of housed households
+    let housed: Vec<&&Household> = households.iter().filter(|h| h.unit.is_some()).collect();
+    let n_owners = housed.iter().filter(|h| h.owns_home(&sim.city)).count() as f32;
+    let n_doma_tenants = housed.iter().filter(|h| sim.city.units[h.unit.unwrap()].is_doma()).count() as f32;
+    let n_renters = housed.len() as f32 - n_owners - n_doma_tenants;
+    let homeowner_debt: f32 = residents.iter().map(|t| t.debt()).sum();
 
-        let nei_n_units = unit_ids.len() as f32;
-        let parcels = &sim.city.residential_parcels_by_neighborhood[neighb_id];
-        n_parcels += parcels.len() as f32;
-        let nei_mean_desirability = parcels
-            .iter()
-            .fold(0., |acc, pos| acc + sim.city.parcels.get(&pos).unwrap().desirability);
+    // Walk each neighborhood's units in parallel,
+    // then combine in neighborhood order
+    let all_totals: Vec<NeighborhoodTotals> = sim.city.units_by_neighborhood
+        .par_iter()
+        .enumerate()
+        .map(|(neighb_id, unit_ids)| neighborhood_totals(sim, neighb_id, unit_ids))
+        .collect();
 
-        neighborhood_stats.insert(
-            neighb_id,
-            json!({
-                "percent_vacant": nei_n_vacant/nei_n_units,
-                "mean_rent": nei_mean_rent/nei_n_units,
-                "mean_rent_per_tenant": nei_mean_rent_per_tenant/(nei_n_tenants as f32),
-                "mean_rent_per_area": nei_mean_rent_per_area/nei_n_units,
-                "mean_adjusted_rent_per_area": nei_mean_adjusted_rent_per_area/nei_n_units,
-                "mean_value_per_area": nei_mean_value_per_area/nei_n_units,
-                "mean_months_vacant": nei_mean_months_vacant/nei_n_units,
-                "mean_rent_income_ratio": if nei_n_tenants > 0 {
-                    nei_mean_rent_income_ratio/nei_n_tenants as f32
-                } else { 0. },
-                "mean_desirability": nei_mean_desirability/parcels.len() as f32,
-                "doma_units": nei_n_doma
-            }),
-        );
+    let mut neighborhood_stats = HashMap::new();
+    for (neighb_id, totals) in all_totals.into_iter().enumerate() {
+        let nei_stats = match totals.stats {
+            Some(stats) => stats,
+            None => continue
+        };
+        neighborhood_stats.insert(neighb_id, nei_stats);
 
-        n_vacant += nei_n_vacant;
-        mean_rent += nei_mean_rent;
-        mean_rent_per_tenant += nei_mean_rent_per_tenant;
-        mean_rent_per_area += nei_mean_rent_per_area;
-        mean_adjusted_rent_per_area += nei_mean_adjusted_rent_per_area;
-        mean_value_per_area += nei_mean_value_per_area;
-        mean_months_vacant += nei_mean_months_vacant;
-        mean_rent_income_ratio += nei_mean_rent_income_ratio;
-        mean_desirability += nei_mean_desirability;
+        n_housed += totals.n_housed;
+        n_earners += totals.n_earners;
+        n_affordable += totals.n_affordable;
+        n_parcels += totals.n_parcels;
+        n_vacant += totals.n_vacant;
+        mean_rent += totals.rent;
+        mean_rent_per_tenant += totals.rent_per_tenant;
+        mean_rent_per_area += totals.rent_per_area;
+        mean_adjusted_rent_per_area += totals.adjusted_rent_per_area;
+        mean_value_per_area += totals.value_per_area;
+        mean_months_vacant += totals.months_vacant;
+        mean_rent_income_ratio += totals.rent_income_ratio;
+        mean_desirability += totals.desirability;
+        mean_value += totals.value;
+        mean_condition += totals.condition;
+        mean_price_to_rent_ratio += totals.price_to_rent_ratio;
+        mean_offers += totals.offers;
+        min_value = f32::min(min_value, totals.min_value);
+        unique_landlords.extend(totals.owners);
+        for (id, data) in totals.landlord_data {
+            let d = landlord_data.entry(id).or_insert((0., 0.));
+            d.0 += data.0;
+            d.1 += data.1;
+        }
+        for (id, data) in totals.doma_data {
+            let d = doma_data.entry(id).or_insert((0., 0.));
+            d.0 += data.0;
+            d.1 += data.1;
+        }
     }
 
     let mut landlord_stats = HashMap::new();
@@ -160,6 +277,11 @@ pub fn stats(sim: &Simulation) -> Value {
         landlord_stats.insert(
             landlord.id as i32,
             json!({
+                "strategy": landlord.strategy.to_string(),
+                "cash": landlord.cash,
+                "debt": landlord.debt(),
+                "income": landlord.income,
+                "expenses": landlord.expenses,
                 "n_units": l_n_units,
                 "p_units": l_n_units/n_units,
                 "mean_condition": data.0/l_n_units,
@@ -168,26 +290,93 @@ pub fn stats(sim: &Simulation) -> Value {
         );
     }
 
-    // DOMA special id of -1
-    let n_doma_units = sim.doma.units.len() as f32;
-    landlord_stats.insert(
-        -1,
-        json!({
-            "n_units": n_doma_units,
-            "p_units": n_doma_units/n_units,
-            "mean_condition": doma_data.0/n_doma_units,
-            "mean_adjusted_rent_per_area": doma_data.1/n_doma_units
-        }),
-    );
+    // DOMAs have special ids of -1, -2, ...
+    for doma in &sim.domas {
+        let data = doma_data.entry(doma.id).or_insert((0., 0.));
+        let n_doma_units = doma.units.len() as f32;
+        landlord_stats.insert(
+            -1 - doma.id as i32,
+            json!({
+                "strategy": doma.strategy.to_string(),
+                "neighborhoods": doma.neighborhoods,
+                "merged_into": doma.merged_into,
+                "members": doma.shares.len(),
+                "raised": doma.raised,
+                "p_dividend": 1.0 - doma.p_reserves - doma.p_expenses,
+                "p_rent_share": doma.p_rent_share,
+                "rent_income_limit": doma.rent_income_limit,
+                "funds": doma.funds,
+                "debt": doma.debt(),
+                "bonds": doma.bonds.iter().map(|b| b.principal).sum::<f32>(),
+                "debt_service": doma.debt_service,
+                "leverage": doma.debt()/f32::max(doma.portfolio_value(&sim.city), 1.),
+                "share_price": doma.share_price(&sim.city, &sim.conf),
+                "unvested": doma.unvested(),
+                "n_redemptions": sim.events.events.iter().filter(|e| match e.kind {
+                    EventKind::DOMASharesRedeemed { doma: d_id, .. } => d_id == doma.id,
+                    _ => false
+                }).count(),
+                "n_share_trades": sim.events.events.iter().filter(|e| match e.kind {
+                    EventKind::DOMASharesTraded { doma: d_id, .. } => d_id == doma.id,
+                    _ => false
+                }).count(),
+                "n_units": n_doma_units,
+                "n_listed": doma.listed.len(),
+                "n_sold": sim.events.events.iter().filter(|e| match e.kind {
+                    EventKind::Sale { seller, .. } => seller == (AgentType::DOMA, doma.id),
+                    _ => false
+                }).count(),
+                "p_units": n_doma_units/n_units,
+                "mean_condition": data.0/n_doma_units,
+                "mean_adjusted_rent_per_area": data.1/n_doma_units
+            }),
+        );
+    }
+
+    // Across all DOMAs, with rates averaged
+    let active_domas: Vec<&DOMA> = sim.domas.iter().filter(|d| d.active()).collect();
+    let n_active_domas = active_domas.len() as f32;
+    let doma_members: HashSet<usize> = sim.domas.iter()
+        .flat_map(|d| d.shares.keys().cloned())
+        .collect();
+    let rent_income_limits: Vec<f32> = active_domas.iter()
+        .filter_map(|d| d.rent_income_limit)
+        .collect();
 
     json!({
-        "population": sim.tenants.len(),
-        "percent_homeless": 1. - n_housed/sim.tenants.len() as f32,
+        "population": population,
+        "percent_homeless": 1. - n_housed/population,
         "percent_vacant": n_vacant/n_units,
-        "percent_affordable": n_affordable/n_housed,
+        "percent_affordable": n_affordable/n_earners,
         "n_units": n_units,
         "p_units": 1.,
         "mean_income": mean_income,
+        "percent_unemployed": n_unemployed/(n_employed + n_unemployed),
+        "percent_retired": n_retired/population,
+        "percent_children": n_children/population,
+        "n_job_lost": sim.events.count("JobLost"),
+        "n_households": n_households,
+        "mean_household_size": population/n_households,
+        "n_births": sim.events.count("Born"),
+        "n_deaths": sim.events.count("Died"),
+        "n_arrivals": sim.events.count("ArrivedInCity"),
+        "n_departures": sim.events.count("LeftCity"),
+        "n_displaced": sim.events.events.iter().filter(|e| match e.kind {
+            EventKind::LeftCity { reason, .. } => reason != DepartureReason::Other,
+            _ => false
+        }).count(),
+        "percent_single": p_household_kind(HouseholdKind::Single),
+        "percent_family": p_household_kind(HouseholdKind::Family),
+        "percent_roommates": p_household_kind(HouseholdKind::Roommates),
+        "percent_owners": n_owners/housed.len() as f32,
+        "percent_renters": n_renters/housed.len() as f32,
+        "percent_doma_tenants": n_doma_tenants/housed.len() as f32,
+        "n_home_purchases": sim.events.events.iter().filter(|e| match e.kind {
+            EventKind::Sale { buyer, .. } => buyer.0 == AgentType::Tenant,
+            _ => false
+        }).count(),
+        "homeowner_debt": homeowner_debt,
+        "percent_households_homeless": households.iter().filter(|h| h.unit.is_none()).count() as f32/n_households,
         "mean_rent": mean_rent/n_units,
         "mean_rent_per_tenant": mean_rent_per_tenant/n_housed,
         "mean_rent_per_area": mean_rent_per_area/n_units,
@@ -198,18 +387,50 @@ pub fn stats(sim: &Simulation) -> Value {
         "min_value": min_value,
         "mean_condition": mean_condition/n_units,
         "mean_price_to_rent_ratio": mean_price_to_rent_ratio/n_units,
-        "mean_rent_income_ratio": if n_housed > 0. { mean_rent_income_ratio/n_housed } else { 0. },
+        "mean_rent_income_ratio": if n_earners > 0. { mean_rent_income_ratio/n_earners } else { 0. },
         "mean_offers": mean_offers/n_units,
         "unique_landlords": unique_landlords.len(),
-        "doma_members": sim.doma.shares.len(),
-        "doma_members_p": sim.doma.shares.len() as f32/sim.tenants.len() as f32,
-        "doma_raised": sim.doma.raised,
-        "doma_property_fund": sim.doma.funds,
-        "doma_p_dividend": 1.0 - sim.doma.p_reserves - sim.doma.p_expenses,
+        "n_domas": n_active_domas,
+        "doma_members": doma_members.len(),
+        "doma_members_p": doma_members.len() as f32/population,
+        "doma_raised": sim.domas.iter().map(|d| d.raised).sum::<f32>(),
+        "doma_property_fund": sim.domas.iter().map(|d| d.funds).sum::<f32>(),
+        "doma_p_dividend": active_domas.iter().map(|d| 1.0 - d.p_reserves - d.p_expenses).sum::<f32>()/n_active_domas,
+        "doma_p_rent_share": active_domas.iter().map(|d| d.p_rent_share).sum::<f32>()/n_active_domas,
+        "doma_rent_income_limit": if !rent_income_limits.is_empty() {
+            Some(rent_income_limits.iter().sum::<f32>()/rent_income_limits.len() as f32)
+        } else {
+            None
+        },
         "mean_desirability": mean_desirability/n_parcels,
         // 'doma_total_dividend_payout': self.doma.last_payout,
-        // 'n_sales': sum(t.sales for t in self.landlords + self.tenants),
-        // 'n_moved': sum(1 for t in self.tenants if t.moved),
+        "n_sales": sim.events.count("Sale"),
+        "n_moved": sim.events.count("MovedIn"),
+        "n_evicted": sim.events.count("Evicted"),
+        "n_no_fault_evicted": sim.events.events.iter().filter(|e| match e.kind {
+            EventKind::Evicted { reason, .. } => reason.no_fault(),
+            _ => false
+        }).count(),
+        "n_eviction_filings": sim.events.count("EvictionFiled"),
+        "n_evictions_dismissed": sim.events.count("EvictionDismissed"),
+        "n_pending_evictions": n_pending_evictions,
+        "percent_in_arrears": if n_occupied > 0. { n_in_arrears/n_occupied } else { 0. },
+        "total_arrears": total_arrears,
+        "total_rent_paid": rent_paid,
+        "mean_rent_burden": mean_rent_burden,
+        "total_savings": total_savings,
+        "mean_savings": total_savings/savings.len() as f32,
+        "median_savings": median_savings,
+        "percent_without_savings": n_without_savings/savings.len() as f32,
+        "percent_ever_evicted": n_ever_evicted/population,
+        "n_foreclosures": sim.events.count("Foreclosure"),
+        "landlord_cash": sim.landlords.iter().map(|l| l.cash).sum::<f32>(),
+        "landlord_debt": sim.landlords.iter().map(|l| l.debt()).sum::<f32>(),
+        "interest_rate": sim.economy.interest_rate,
+        "inflation": sim.economy.inflation,
+        "credit": sim.economy.credit,
+        "price_level": sim.economy.price_level,
+        "market_price_to_rent_ratio": sim.economy.price_to_rent_ratio,
         // 'mean_doma_rent_vs_market_rent': 0 if not landlord_units or not self.doma.units else np.mean([u.adjusted_rent_per_area for u in self.doma.units])/np.mean([u.adjusted_rent_per_area for u in landlord_units]),
         "landlords": landlord_stats,
         "neighborhoods": neighborhood_stats
@@ -0,0 +1,265 @@
+use super::config::{self, ConfigError};
+use super::design::{self, DesignError, DesignSource, LoadError};
+use super::run;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::Deserialize;
+use serde_json::Value;
+use serde_yaml::Value as YamlValue;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+
+// A sweep runs every combination of `grid` values
+// on top of each entry in `runs`, once for each seed
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "UPPERCASE", default)]
+pub struct SweepSpec {
+    // Config key -> values to try
+    pub grid: BTreeMap<String, Vec<YamlValue>>,
+
+    // Explicit sets of config overrides
+    pub runs: Vec<BTreeMap<String, YamlValue>>,
+
+    pub seeds: Option<usize>,
+    pub steps: Option<usize>,
+}
+
+#[derive(Debug)]
+pub enum SweepError {
+    Io(io::Error),
+    Spec(serde_yaml::Error),
+    MalformedVary(String),
+    Config(ConfigError),
+    Design { run: String, err: LoadError },
+    InvalidDesign { run: String, err: DesignError },
+}
+
+impl fmt::Display for SweepError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SweepError::Io(err) => write!(f, "could not open sweep spec: {}", err),
+            SweepError::Spec(err) => write!(f, "could not read sweep spec: {}", err),
+            SweepError::MalformedVary(arg) =>
+                write!(f, "sweep values must be of the form KEY=v1,v2,..., got {:?}", arg),
+            SweepError::Config(err) => write!(f, "{}", err),
+            SweepError::Design { run, err } => write!(f, "run {}: {}", run, err),
+            SweepError::InvalidDesign { run, err } => write!(f, "run {}: {}", run, err),
+        }
+    }
+}
+
+impl From<ConfigError> for SweepError {
+    fn from(err: ConfigError) -> SweepError {
+        SweepError::Config(err)
+    }
+}
+
+pub fn load_spec(path: &Path) -> Result<SweepSpec, SweepError> {
+    let file = File::open(path).map_err(SweepError::Io)?;
+    let reader = BufReader::new(file);
+    serde_yaml::from_reader(reader).map_err(SweepError::Spec)
+}
+
+// YAML values as override strings,
+// so they can go through `config::load_config`.
+// Anything else is written as JSON, which is flow-style
+// YAML, so sequences and mappings stay on one line.
+fn yaml_to_string(val: &YamlValue) -> String {
+    match val {
+        YamlValue::String(s) => s.clone(),
+        _ => serde_json::to_string(val).unwrap()
+    }
+}
+
+// Quote CSV fields that contain
+// separators, quotes or newlines
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace("\"", "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn csv_row(fields: &[String]) -> String {
+    fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(",")
+}
+
+impl SweepSpec {
+    // Add `KEY=v1,v2,...` values to the grid
+    pub fn vary(&mut self, key: String, vals: Vec<String>) {
+        let vals = vals.into_iter().map(YamlValue::String).collect();
+        self.grid.insert(key, vals);
+    }
+
+    // Every set of overrides this sweep covers
+    pub fn combinations(&self) -> Vec<Vec<(String, String)>> {
+        let mut combos: Vec<Vec<(String, String)>> = if self.runs.is_empty() {
+            vec![Vec::new()]
+        } else {
+            self.runs.iter().map(|run| {
+                run.iter().map(|(key, val)| (key.clone(), yaml_to_string(val))).collect()
+            }).collect()
+        };
+        for (key, vals) in &self.grid {
+            combos = combos.into_iter().flat_map(|combo| {
+                vals.iter().map(move |val| {
+                    let mut combo = combo.clone();
+                    combo.push((key.clone(), yaml_to_string(val)));
+                    combo
+                })
+            }).collect();
+        }
+        combos
+    }
+}
+
+// Parse `KEY=v1,v2,...` strings
+pub fn parse_vary<'a, I: Iterator<Item=&'a str>>(args: I) -> Result<Vec<(String, Vec<String>)>, SweepError> {
+    args.map(|arg| {
+        let parts: Vec<&str> = arg.splitn(2, "=").collect();
+        if parts.len() != 2 {
+            return Err(SweepError::MalformedVary(arg.to_string()));
+        }
+        let vals = parts[1].split(",").map(|v| v.trim().to_string()).collect();
+        Ok((parts[0].to_string(), vals))
+    }).collect()
+}
+
+struct SweepRun {
+    name: String,
+    overrides: Vec<(String, String)>,
+    seed: u64,
+}
+
+pub struct SweepOptions {
+    pub config_path: PathBuf,
+
+    // Overrides applied to every run,
+    // before the sweep's own
+    pub overrides: Vec<(String, String)>,
+
+    pub design_id: Option<String>,
+    pub design_source: Option<String>,
+    pub seed: u64,
+    pub steps: usize,
+    pub output: PathBuf,
+
+    // Number of runs in parallel,
+    // 0 for one per core
+    pub jobs: usize,
+}
+
+// Run the sweep, writing each run to its own directory
+// and a `summary.csv` of final stats, keyed by parameter values
+pub fn sweep(spec: &SweepSpec, opts: &SweepOptions) -> Result<(), SweepError> {
+    let n_seeds = spec.seeds.unwrap_or(1);
+    let steps = spec.steps.unwrap_or(opts.steps);
+    let mut seed_rng: StdRng = SeedableRng::seed_from_u64(opts.seed);
+    let seeds: Vec<u64> = (0..n_seeds).map(|_| seed_rng.gen()).collect();
+
+    let combos = spec.combinations();
+    let mut runs = Vec::new();
+    for combo in &combos {
+        for &seed in &seeds {
+            runs.push(SweepRun {
+                name: format!("{:04}", runs.len()),
+                overrides: combo.clone(),
+                seed,
+            });
+        }
+    }
+    println!("Sweeping {} combinations x {} seeds = {} runs", combos.len(), n_seeds, runs.len());
+
+    // Load every run's config and design first, so a bad
+    // override or design fails before anything is run
+    let mut setups = Vec::new();
+    for r in &runs {
+        let mut overrides = opts.overrides.clone();
+        overrides.extend(r.overrides.iter().cloned());
+        let mut conf = config::load_config(&opts.config_path, &overrides)?;
+        conf.debug = true;
+        conf.seed = r.seed;
+        conf.steps = steps;
+        if let Some(design_id) = &opts.design_id {
+            conf.design_id = design_id.clone();
+        }
+        if let Some(source) = &opts.design_source {
+            conf.design_source = source.clone();
+        }
+
+        let source = DesignSource::parse(&conf.design_source);
+        let design = design::load_design(&source, &conf.design_id)
+            .map_err(|err| SweepError::Design { run: r.name.clone(), err })?;
+        if let Some(err) = design.validate().into_iter().find(|err| err.is_fatal()) {
+            return Err(SweepError::InvalidDesign { run: r.name.clone(), err });
+        }
+        setups.push((design, conf));
+    }
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(opts.jobs)
+        .build()
+        .unwrap();
+    let finals: Vec<Value> = pool.install(|| {
+        runs.par_iter().zip(setups).map(|(r, (design, conf))| {
+            let output = run::run(design, conf, |_| {})
+                .map_err(|err| SweepError::InvalidDesign { run: r.name.clone(), err })?;
+            run::write_run_data(&output, &opts.output.join(&r.name));
+            println!("Finished run {}", r.name);
+            Ok(match output.history.last() {
+                Some(last) => last.clone(),
+                None => output.init.clone()
+            })
+        }).collect::<Result<_, SweepError>>()
+    })?;
+
+    write_summary(&runs, &finals, &opts.output.join("summary.csv"));
+    println!("Wrote sweep to {:?}", opts.output);
+    Ok(())
+}
+
+// One row per run: its parameters, seed,
+// and the scalar stats of its final step
+fn write_summary(runs: &[SweepRun], finals: &[Value], path: &Path) {
+    let mut param_keys: Vec<&String> = runs.iter()
+        .flat_map(|r| r.overrides.iter().map(|(key, _)| key))
+        .collect();
+    param_keys.sort();
+    param_keys.dedup();
+
+    let mut stat_keys: Vec<&String> = finals.iter()
+        .filter_map(|stats| stats.as_object())
+        .flat_map(|stats| stats.iter().filter(|(_, v)| v.is_number()).map(|(k, _)| k))
+        .collect();
+    stat_keys.sort();
+    stat_keys.dedup();
+
+    let mut header = vec!["run".to_string(), "seed".to_string()];
+    header.extend(param_keys.iter().map(|k| k.to_string()));
+    header.extend(stat_keys.iter().map(|k| k.to_string()));
+    let mut lines = vec![csv_row(&header)];
+    for (r, stats) in runs.iter().zip(finals) {
+        let mut row = vec![r.name.clone(), r.seed.to_string()];
+        for key in &param_keys {
+            let val = r.overrides.iter().rev()
+                .find(|(k, _)| k == *key)
+                .map(|(_, v)| v.clone())
+                .unwrap_or_default();
+            row.push(val);
+        }
+        for key in &stat_keys {
+            row.push(match stats.get(key.as_str()) {
+                Some(v) if v.is_number() => v.to_string(),
+                _ => String::new()
+            });
+        }
+        lines.push(csv_row(&row));
+    }
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, lines.join("\n") + "\n").expect("Unable to write file");
+}
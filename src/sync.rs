@@ -80,8 +80,8 @@ pub fn sync(month: usize, city: &City, design: &Design, stats: Value) -> redis::
     let state_serialized = jsonify(month, city, design, stats).to_string();
     let hash = md5::Md5::digest(state_serialized.as_bytes());
 
-    con.set("state", state_serialized)?;
-    con.set("state:key", format!("{:X}", hash))?;
+    let _: () = con.set("state", state_serialized)?;
+    let _: () = con.set("state:key", format!("{:X}", hash))?;
 
     Ok(())
 }
@@ -0,0 +1,34 @@
+// Not every test uses every helper
+#![allow(dead_code)]
+
+use doma_sim::config::{self, Config};
+use doma_sim::generate::{generate, GeneratorConfig};
+use doma_sim::{Design, Simulation};
+use std::path::{Path, PathBuf};
+
+pub fn config_path() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("config.yaml")
+}
+
+pub fn config(overrides: &[(&str, &str)]) -> Config {
+    let overrides: Vec<(String, String)> = overrides.iter()
+        .map(|&(key, val)| (key.to_string(), val.to_string()))
+        .collect();
+    config::load_config(&config_path(), &overrides).unwrap()
+}
+
+// Small enough to step quickly in debug builds
+pub fn small_design(seed: u64) -> Design {
+    generate(seed, &GeneratorConfig {
+        rows: 8,
+        cols: 8,
+        neighborhoods: 3,
+        ..GeneratorConfig::default()
+    })
+}
+
+pub fn small_sim(seed: u64) -> Simulation {
+    let mut conf = config(&[]);
+    conf.seed = seed;
+    Simulation::new(small_design(1), conf).unwrap()
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use doma_sim::config::{self, ConfigError};
+
+fn load(overrides: &[&str]) -> Result<doma_sim::Config, ConfigError> {
+    let overrides = config::parse_overrides(overrides.iter().cloned())?;
+    config::load_config(&common::config_path(), &overrides)
+}
+
+#[test]
+fn overrides_are_applied() {
+    let conf = load(&["MORTGAGE_RATE=0.07", "DOMA_RENT_INCOME_LIMIT=0.3", "JUST_CAUSE=true"]).unwrap();
+    assert_eq!(conf.mortgage_rate, 0.07);
+    assert_eq!(conf.doma_rent_income_limit, Some(0.3));
+    assert!(conf.just_cause);
+}
+
+#[test]
+fn override_keys_are_case_insensitive() {
+    let conf = load(&["mortgage_rate=0.07"]).unwrap();
+    assert_eq!(conf.mortgage_rate, 0.07);
+}
+
+#[test]
+fn overrides_are_parsed_as_yaml() {
+    let conf = load(&["DOMA_RENT_INCOME_LIMIT=null", "RATE_SCHEDULE=[[12, 0.05]]"]).unwrap();
+    assert_eq!(conf.doma_rent_income_limit, None);
+    assert_eq!(conf.rate_schedule, Some(vec![(12, 0.05)]));
+}
+
+#[test]
+fn later_overrides_win() {
+    let conf = load(&["MORTGAGE_RATE=0.07", "MORTGAGE_RATE=0.02"]).unwrap();
+    assert_eq!(conf.mortgage_rate, 0.02);
+}
+
+#[test]
+fn keys_with_defaults_can_be_overridden() {
+    // Not in the config file
+    let conf = load(&["STEPS=36"]).unwrap();
+    assert_eq!(conf.steps, 36);
+}
+
+#[test]
+fn unknown_keys_are_rejected() {
+    match load(&["MORTGAGE_RAET=0.07"]) {
+        Err(ConfigError::UnknownKey(key)) => assert_eq!(key, "MORTGAGE_RAET"),
+        other => panic!("expected an unknown key error, got {:?}", other),
+    }
+}
+
+#[test]
+fn malformed_overrides_are_rejected() {
+    assert!(matches!(load(&["MORTGAGE_RATE"]), Err(ConfigError::MalformedOverride(_))));
+}
+
+#[test]
+fn invalid_values_are_rejected() {
+    assert!(matches!(load(&["MORTGAGE_RATE=[1, 2"]), Err(ConfigError::InvalidOverride { .. })));
+    assert!(matches!(load(&["MORTGAGE_RATE=high"]), Err(ConfigError::Yaml(_))));
+}
+
+#[test]
+fn missing_keys_take_shipped_values() {
+    // Written before most of the current keys existed
+    let path = std::env::temp_dir().join(format!("doma_sim_test_{}.yaml", std::process::id()));
+    std::fs::write(&path, "DESIGN_ID: \"oldtown\"\nDOMA_P_RENT_SHARE: 0.2\n").unwrap();
+    let conf = config::load_config(&path, &[("JUST_CAUSE".to_string(), "true".to_string())]);
+    std::fs::remove_file(&path).unwrap();
+    let conf = conf.unwrap();
+    let shipped = load(&[]).unwrap();
+    assert_eq!(conf.design_id, "oldtown");
+    assert_eq!(conf.doma_p_rent_share, 0.2);
+    assert_eq!(conf.mortgage_rate, shipped.mortgage_rate);
+    assert_eq!(conf.doma_voting_rule, shipped.doma_voting_rule);
+    assert!(conf.just_cause);
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use doma_sim::design::DesignError;
+use doma_sim::Simulation;
+
+#[test]
+fn generated_designs_are_valid() {
+    for seed in 0..10 {
+        assert_eq!(common::small_design(seed).validate(), Vec::new());
+    }
+}
+
+#[test]
+fn empty_layout() {
+    let mut design = common::small_design(0);
+    design.map.layout.clear();
+    assert!(design.validate().contains(&DesignError::EmptyLayout));
+}
+
+#[test]
+fn ragged_rows() {
+    let mut design = common::small_design(0);
+    design.map.layout[2].pop();
+    assert!(design.validate().contains(&DesignError::RaggedRow { row: 2, len: 7, expected: 8 }));
+}
+
+#[test]
+fn bad_parcels() {
+    let mut design = common::small_design(0);
+    design.map.layout[0][0] = Some("Residential".to_string());
+    design.map.layout[0][1] = Some("x|Residential".to_string());
+    design.map.layout[0][2] = Some("0|Skyscraper".to_string());
+    design.map.layout[0][3] = Some("99|Residential".to_string());
+    let errors = design.validate();
+    assert!(errors.contains(&DesignError::MalformedParcel {
+        row: 0, col: 0, cell: "Residential".to_string() }));
+    assert!(errors.contains(&DesignError::InvalidNeighborhoodId {
+        row: 0, col: 1, id: "x".to_string() }));
+    assert!(errors.contains(&DesignError::UnknownParcelType {
+        row: 0, col: 2, typ: "Skyscraper".to_string() }));
+    assert!(errors.contains(&DesignError::UnknownNeighborhood { row: 0, col: 3, id: 99 }));
+}
+
+#[test]
+fn unknown_neighborhoods_arent_fatal() {
+    let mut design = common::small_design(0);
+    design.map.layout[0][0] = Some("99|Residential".to_string());
+    let errors = design.validate();
+    assert_eq!(errors.len(), 1);
+    assert!(!errors[0].is_fatal());
+}
+
+#[test]
+fn bad_neighborhood_specs() {
+    let mut design = common::small_design(0);
+    {
+        let neighb = design.neighborhoods.get_mut(&1).unwrap();
+        neighb.min_units = 10;
+        neighb.max_units = 5;
+        neighb.min_area = 0;
+        neighb.sqm_per_occupant = 0;
+        neighb.p_commercial = 1.;
+    }
+    let errors = design.validate();
+    assert!(errors.contains(&DesignError::InvalidUnitRange { neighborhood: 1, min: 10, max: 5 }));
+    assert!(errors.contains(&DesignError::InvalidAreaRange {
+        neighborhood: 1, min: 0, max: design.neighborhoods[&1].max_area }));
+    assert!(errors.contains(&DesignError::InvalidSqmPerOccupant { neighborhood: 1 }));
+    assert!(errors.contains(&DesignError::InvalidCommercialShare { neighborhood: 1, p: 1. }));
+    assert!(errors.iter().all(|err| err.is_fatal()));
+}
+
+#[test]
+fn no_commercial_space() {
+    let mut design = common::small_design(0);
+    for neighb in design.neighborhoods.values_mut() {
+        neighb.p_commercial = 0.;
+    }
+    assert_eq!(design.validate(), vec![DesignError::NoCommercial]);
+
+    // Buildings need more than 3 units for commercial floors,
+    // and unit counts are random, so the minimum has to allow them
+    let mut design = common::small_design(0);
+    for neighb in design.neighborhoods.values_mut() {
+        neighb.min_units = 2;
+        neighb.max_units = 6;
+    }
+    assert_eq!(design.validate(), vec![DesignError::NoCommercial]);
+    design.neighborhoods.get_mut(&0).unwrap().min_units = 4;
+    assert_eq!(design.validate(), Vec::new());
+}
+
+#[test]
+fn cities_without_commercial_floors_fail_to_start() {
+    let mut design = common::small_design(0);
+    for neighb in design.neighborhoods.values_mut() {
+        neighb.p_commercial = 0.;
+    }
+    let result = Simulation::new(design, common::config(&[]));
+    assert!(matches!(result, Err(DesignError::NoCommercial)));
+}
@@ -0,0 +1,24 @@
+mod common;
+
+use doma_sim::run;
+
+const STEPS: usize = 6;
+
+#[test]
+fn same_seed_same_run_on_any_number_of_threads() {
+    let first = run::trace(common::small_sim(3), STEPS);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(rayon::current_num_threads() + 1)
+        .build()
+        .unwrap();
+    let second = pool.install(|| run::trace(common::small_sim(3), STEPS));
+    assert_eq!(first.len(), STEPS + 1);
+    assert!(run::first_divergence(&first, &second).is_none());
+}
+
+#[test]
+fn different_seeds_diverge() {
+    let first = run::trace(common::small_sim(3), STEPS);
+    let second = run::trace(common::small_sim(4), STEPS);
+    assert!(run::first_divergence(&first, &second).is_some());
+}
@@ -0,0 +1,141 @@
+mod common;
+
+use doma_sim::agent::{DOMASpec, Employment, DOMA};
+use doma_sim::events::{EventKind, EventLog};
+use doma_sim::Simulation;
+
+fn doma(sim: &Simulation, funds: f32) -> DOMA {
+    let spec = DOMASpec {
+        starting_funds: funds,
+        neighborhoods: Vec::new(),
+        p_rent_share: None,
+        p_reserves: None,
+        rent_income_limit: None,
+        strategy: None,
+    };
+    // An id no unit is owned by
+    DOMA::new(99, &spec, &sim.conf)
+}
+
+fn adults(sim: &Simulation, n: usize) -> Vec<usize> {
+    sim.tenants.iter()
+        .filter(|t| !t.player && !t.departed && t.employment != Employment::Child)
+        .map(|t| t.id)
+        .take(n)
+        .collect()
+}
+
+#[test]
+fn shares_vest_after_the_vesting_period() {
+    let mut sim = common::small_sim(1);
+    sim.conf.doma_vesting_months = 12;
+    let mut doma = doma(&sim, 0.);
+    doma.credit_shares(0, 10., 5, &sim.conf);
+    assert_eq!(doma.unvested(), 10.);
+
+    doma.vest(16);
+    assert!(!doma.shares.contains_key(&0));
+    doma.vest(17);
+    assert_eq!(doma.shares[&0], 10.);
+    assert_eq!(doma.unvested(), 0.);
+    assert!(doma.vesting.is_empty());
+}
+
+#[test]
+fn shares_vest_immediately_without_a_vesting_period() {
+    let sim = common::small_sim(1);
+    let mut doma = doma(&sim, 0.);
+    doma.credit_shares(0, 10., 5, &sim.conf);
+    assert_eq!(doma.shares[&0], 10.);
+}
+
+#[test]
+fn redeeming_nothing_keeps_unvested_shares() {
+    let mut sim = common::small_sim(1);
+    let mut doma = doma(&sim, 1000.);
+    let mut events = EventLog::default();
+    doma.shares.insert(0, 10.);
+    doma.vesting.insert(0, vec![(5., 100)]);
+    let savings = sim.tenants[0].savings;
+    doma.redeem(&mut sim.tenants[0], 0., 2., &mut events);
+    assert_eq!(doma.shares[&0], 10.);
+    assert_eq!(doma.unvested(), 5.);
+    assert_eq!(sim.tenants[0].savings, savings);
+    assert!(events.events.is_empty());
+}
+
+#[test]
+fn redeeming_pays_out_vested_shares_and_forfeits_unvested() {
+    let mut sim = common::small_sim(1);
+    let mut doma = doma(&sim, 1000.);
+    let mut events = EventLog::default();
+    doma.shares.insert(0, 10.);
+    doma.vesting.insert(0, vec![(5., 100)]);
+    let savings = sim.tenants[0].savings;
+    doma.redeem(&mut sim.tenants[0], 0.5, 2., &mut events);
+    assert_eq!(doma.shares[&0], 5.);
+    assert_eq!(doma.unvested(), 0.);
+    assert_eq!(doma.funds, 990.);
+    assert_eq!(sim.tenants[0].savings, savings + 10.);
+}
+
+#[test]
+fn redemptions_are_limited_by_funds() {
+    let mut sim = common::small_sim(1);
+    let mut doma = doma(&sim, 4.);
+    let mut events = EventLog::default();
+    doma.shares.insert(0, 10.);
+    doma.redeem(&mut sim.tenants[0], 1., 2., &mut events);
+    assert_eq!(doma.shares[&0], 8.);
+    assert_eq!(doma.funds, 0.);
+}
+
+#[test]
+fn members_short_on_cash_sell_to_members_with_savings() {
+    let mut sim = common::small_sim(1);
+    let mut doma = doma(&sim, 0.);
+    let mut events = EventLog::default();
+    let ids = adults(&sim, 2);
+    let (seller, buyer) = (ids[0], ids[1]);
+    sim.tenants[seller].income = 1000.;
+    sim.tenants[seller].savings = 0.;
+    sim.tenants[buyer].income = 100.;
+    sim.tenants[buyer].savings = 100. * 12. + 500.;
+    doma.shares.insert(seller, 10.);
+    doma.shares.insert(buyer, 1.);
+
+    doma.trade_shares(&mut sim.tenants, &sim.city, 10., &mut events);
+    assert!(!doma.shares.contains_key(&seller));
+    assert_eq!(doma.shares[&buyer], 11.);
+    assert_eq!(sim.tenants[seller].savings, 100.);
+    assert_eq!(sim.tenants[buyer].savings, 100. * 12. + 400.);
+    assert!(matches!(events.events[0].kind,
+                     EventKind::DOMASharesTraded { shares, price, .. } if shares == 10. && price == 10.));
+}
+
+#[test]
+fn shares_are_split_among_heirs() {
+    let sim = common::small_sim(1);
+    let mut doma = doma(&sim, 0.);
+    doma.shares.insert(0, 9.);
+    doma.vesting.insert(0, vec![(3., 20), (6., 30)]);
+    doma.shares.insert(1, 1.);
+    doma.bequeath(0, &[1, 2, 3]);
+    assert!(!doma.shares.contains_key(&0));
+    assert!(!doma.vesting.contains_key(&0));
+    assert_eq!(doma.shares[&1], 4.);
+    assert_eq!(doma.shares[&2], 3.);
+    assert_eq!(doma.shares[&3], 3.);
+    assert_eq!(doma.vesting[&2], vec![(1., 20), (2., 30)]);
+}
+
+#[test]
+fn shares_without_heirs_are_written_off() {
+    let sim = common::small_sim(1);
+    let mut doma = doma(&sim, 0.);
+    doma.shares.insert(0, 9.);
+    doma.vesting.insert(0, vec![(3., 20)]);
+    doma.bequeath(0, &[]);
+    assert!(doma.shares.is_empty());
+    assert!(doma.vesting.is_empty());
+}
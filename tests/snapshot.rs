@@ -0,0 +1,38 @@
+mod common;
+
+use doma_sim::snapshot::{self, SnapshotError};
+use std::env;
+use std::fs;
+
+#[test]
+fn restored_snapshot_continues_like_a_straight_run() {
+    let mut straight = common::small_sim(7);
+    for _ in 0..6 {
+        straight.step();
+    }
+
+    let mut sim = common::small_sim(7);
+    for _ in 0..3 {
+        sim.step();
+    }
+    let path = env::temp_dir().join(format!("doma_sim_test_{}.snap", std::process::id()));
+    snapshot::save_snapshot(&sim, &path).unwrap();
+    let mut restored = snapshot::load_snapshot(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(restored.state_hashes(), sim.state_hashes());
+
+    for _ in 0..3 {
+        restored.step();
+    }
+    assert_eq!(restored.time, straight.time);
+    assert_eq!(restored.state_hashes(), straight.state_hashes());
+}
+
+#[test]
+fn rejects_files_that_arent_snapshots() {
+    let path = env::temp_dir().join(format!("doma_sim_test_{}.bad", std::process::id()));
+    fs::write(&path, b"not a snapshot").unwrap();
+    let result = snapshot::load_snapshot(&path);
+    fs::remove_file(&path).unwrap();
+    assert!(matches!(result, Err(SnapshotError::NotASnapshot)));
+}